@@ -1,18 +1,26 @@
 //! Generic traits for `Arc`-like pointer types
 
-use alloc::sync::{Arc, Weak};
+use alloc::sync::Arc;
 use core::{mem::ManuallyDrop, ops::Deref, pin::Pin, ptr};
 
 #[allow(unused_imports)]
 use crate::msrv::StrictProvenance;
 use crate::{atomic::ArcPtrBorrow, NULL};
 
+mod static_ref;
+mod thin;
+
+pub use static_ref::{Align8, MaybeStatic, StaticRef};
+pub use thin::ThinArc;
+
 /// An `Arc`-like pointer.
 ///
 /// # Safety
 ///
 /// Implementors must have the same semantics as [`Arc`]. The pointer
 /// returned by [`into_ptr`](Self::into_ptr) **must be aligned to 4**.
+/// [`is_static`](Self::is_static) must return `true` only for pointers which are not
+/// reference-counted, i.e. which stay valid forever.
 pub unsafe trait ArcPtr: Clone {
     /// Whether this pointer type may be null.
     const NULLABLE: bool = false;
@@ -30,6 +38,14 @@ pub unsafe trait ArcPtr: Clone {
     /// Returns the wrapped pointer without consuming the Arc pointer.
     #[allow(clippy::wrong_self_convention)]
     fn as_ptr(arc: &Self) -> *mut ();
+    /// Returns `true` if the pointer is static, i.e. it is not reference-counted.
+    ///
+    /// Static pointers don't need their ownership to be transferred to concurrent borrows,
+    /// so storing a new Arc in place of a static one doesn't scan the domain.
+    #[inline(always)]
+    fn is_static(_ptr: *mut ()) -> bool {
+        false
+    }
     /// Increments the reference count of the Arc pointer.
     ///
     /// # Safety
@@ -65,6 +81,10 @@ unsafe impl<A: NonNullArcPtr> ArcPtr for Option<A> {
     fn as_ptr(arc: &Self) -> *mut () {
         arc.as_ref().map_or(NULL, A::as_ptr)
     }
+    #[inline(always)]
+    fn is_static(ptr: *mut ()) -> bool {
+        !ptr.is_null() && A::is_static(ptr)
+    }
 }
 
 unsafe impl<A: ArcPtr + Deref> ArcPtr for Pin<A> {
@@ -83,6 +103,10 @@ unsafe impl<A: ArcPtr + Deref> ArcPtr for Pin<A> {
             A::as_ptr(&arc)
         }
     }
+    #[inline(always)]
+    fn is_static(ptr: *mut ()) -> bool {
+        A::is_static(ptr)
+    }
 }
 
 #[cfg(not(target_pointer_width = "16"))]
@@ -101,22 +125,6 @@ unsafe impl<T> ArcPtr for Arc<T> {
     }
 }
 
-#[cfg(not(target_pointer_width = "16"))]
-unsafe impl<T> ArcPtr for Weak<T> {
-    #[inline(always)]
-    unsafe fn from_ptr(ptr: *mut ()) -> Self {
-        unsafe { Weak::from_raw(ptr.cast()) }
-    }
-    #[inline(always)]
-    fn into_ptr(arc: Self) -> *mut () {
-        Weak::into_raw(arc).cast_mut().cast()
-    }
-    #[inline(always)]
-    fn as_ptr(arc: &Self) -> *mut () {
-        Weak::as_ptr(arc).cast_mut().cast()
-    }
-}
-
 /// An [`ArcPtr`] whose [`ArcPtr::into_ptr`] never returns a null pointer.
 ///
 /// # Safety
@@ -0,0 +1,291 @@
+use alloc::sync::Arc;
+use core::{borrow::Borrow, fmt, mem, ops::Deref};
+
+#[allow(unused_imports)]
+use crate::msrv::StrictProvenance;
+use crate::{
+    arc::{ArcPtr, NonNullArcPtr},
+    msrv::ptr,
+};
+
+/// A `&'static` reference usable as an [`ArcPtr`].
+///
+/// It is not reference-counted, so storing it in an `AtomicArc` doesn't allocate, and
+/// swapping it out doesn't scan the domain.
+///
+/// `T` must be aligned to 4, as required by [`ArcPtr`].
+///
+/// # Examples
+///
+/// ```rust
+/// # use hazarc::{arc::StaticRef, write_policy::Concurrent};
+/// # hazarc::domain!(Domain(8));
+/// # type AtomicArcPtr<A> = hazarc::atomic::AtomicArcPtr<A, Domain, Concurrent>;
+/// static DEFAULT: u32 = 42;
+/// let atomic_ref = AtomicArcPtr::new(StaticRef(&DEFAULT));
+/// assert_eq!(**atomic_ref.load(), 42);
+/// ```
+pub struct StaticRef<T: 'static>(pub &'static T);
+
+impl<T> StaticRef<T> {
+    const ALIGNED: () = assert!(mem::align_of::<T>() >= 4, "StaticRef requires align >= 4");
+}
+
+impl<T> Clone for StaticRef<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for StaticRef<T> {}
+
+impl<T> Deref for StaticRef<T> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+impl<T> AsRef<T> for StaticRef<T> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T> Borrow<T> for StaticRef<T> {
+    fn borrow(&self) -> &T {
+        self
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for StaticRef<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T> From<&'static T> for StaticRef<T> {
+    fn from(value: &'static T) -> Self {
+        Self(value)
+    }
+}
+
+#[cfg(not(target_pointer_width = "16"))]
+unsafe impl<T> ArcPtr for StaticRef<T> {
+    #[inline(always)]
+    unsafe fn from_ptr(ptr: *mut ()) -> Self {
+        Self(unsafe { &*ptr.cast() })
+    }
+    #[inline(always)]
+    fn into_ptr(arc: Self) -> *mut () {
+        Self::as_ptr(&arc)
+    }
+    #[inline(always)]
+    fn as_ptr(arc: &Self) -> *mut () {
+        #[allow(clippy::let_unit_value)]
+        let _ = Self::ALIGNED;
+        ptr::from_ref(arc.0).cast_mut().cast()
+    }
+    #[inline(always)]
+    fn is_static(_ptr: *mut ()) -> bool {
+        true
+    }
+    #[inline(always)]
+    unsafe fn incr_rc(_ptr: *mut ()) {}
+    #[inline(always)]
+    unsafe fn decr_rc(_ptr: *mut ()) {}
+}
+
+#[cfg(not(target_pointer_width = "16"))]
+unsafe impl<T> NonNullArcPtr for StaticRef<T> {}
+
+const STATIC_TAG: usize = 0b100;
+
+/// A value aligned to 8, which can be referenced by [`MaybeStatic::Static`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(align(8))]
+pub struct Align8<T>(pub T);
+
+impl<T> Deref for Align8<T> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Either a `&'static` reference or an [`Arc`].
+///
+/// It allows storing static default values in an `AtomicArc` without allocating them, while
+/// still being able to store dynamically allocated ones. The variant is encoded in the third
+/// low bit of the pointer, so static values are wrapped in [`Align8`], and `Arc` allocations
+/// must be aligned to 8 — it is always the case on 64-bit platforms, as the allocation starts
+/// with the reference counters; on other platforms, `T` must be aligned to 8.
+///
+/// # Examples
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// # use hazarc::arc::{Align8, MaybeStatic};
+/// # hazarc::domain!(Domain(8));
+/// # type AtomicMaybeStatic<T> = hazarc::AtomicMaybeStatic<T, Domain>;
+/// static DEFAULT: Align8<u32> = Align8(0);
+/// let atomic_arc = AtomicMaybeStatic::new(MaybeStatic::Static(&DEFAULT));
+/// atomic_arc.store(MaybeStatic::Arc(Arc::new(42)));
+/// assert_eq!(**atomic_arc.load(), 42);
+/// ```
+pub enum MaybeStatic<T: 'static> {
+    /// A static reference.
+    Static(&'static Align8<T>),
+    /// A reference-counted pointer.
+    Arc(Arc<T>),
+}
+
+impl<T> MaybeStatic<T> {
+    // `ArcInner` starts with two `AtomicUsize` counters, so the `Arc` pointer is aligned to 8
+    // if the counters are, or if `T` is.
+    const ALIGNED: () = assert!(
+        mem::align_of::<usize>() >= 8 || mem::align_of::<T>() >= 8,
+        "MaybeStatic requires Arc allocations aligned to 8"
+    );
+}
+
+impl<T> Clone for MaybeStatic<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Static(r) => Self::Static(r),
+            Self::Arc(arc) => Self::Arc(arc.clone()),
+        }
+    }
+}
+
+impl<T> Deref for MaybeStatic<T> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Static(r) => &r.0,
+            Self::Arc(arc) => arc,
+        }
+    }
+}
+
+impl<T> AsRef<T> for MaybeStatic<T> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T> Borrow<T> for MaybeStatic<T> {
+    fn borrow(&self) -> &T {
+        self
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for MaybeStatic<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T> From<&'static Align8<T>> for MaybeStatic<T> {
+    fn from(value: &'static Align8<T>) -> Self {
+        Self::Static(value)
+    }
+}
+
+impl<T> From<Arc<T>> for MaybeStatic<T> {
+    fn from(value: Arc<T>) -> Self {
+        Self::Arc(value)
+    }
+}
+
+#[cfg(not(target_pointer_width = "16"))]
+unsafe impl<T> ArcPtr for MaybeStatic<T> {
+    #[inline(always)]
+    #[allow(unstable_name_collisions)]
+    unsafe fn from_ptr(ptr: *mut ()) -> Self {
+        if Self::is_static(ptr) {
+            Self::Static(unsafe { &*ptr.map_addr(|addr| addr & !STATIC_TAG).cast() })
+        } else {
+            Self::Arc(unsafe { <Arc<T> as ArcPtr>::from_ptr(ptr) })
+        }
+    }
+    #[inline(always)]
+    fn into_ptr(arc: Self) -> *mut () {
+        match arc {
+            Self::Static(_) => Self::as_ptr(&arc),
+            Self::Arc(arc) => <Arc<T> as ArcPtr>::into_ptr(arc),
+        }
+    }
+    #[inline(always)]
+    #[allow(unstable_name_collisions)]
+    fn as_ptr(arc: &Self) -> *mut () {
+        #[allow(clippy::let_unit_value)]
+        let _ = Self::ALIGNED;
+        match arc {
+            Self::Static(r) => {
+                (ptr::from_ref(*r).cast_mut().cast::<()>()).map_addr(|addr| addr | STATIC_TAG)
+            }
+            Self::Arc(arc) => <Arc<T> as ArcPtr>::as_ptr(arc),
+        }
+    }
+    #[inline(always)]
+    #[allow(unstable_name_collisions)]
+    fn is_static(ptr: *mut ()) -> bool {
+        ptr.addr() & STATIC_TAG != 0
+    }
+}
+
+#[cfg(not(target_pointer_width = "16"))]
+unsafe impl<T> NonNullArcPtr for MaybeStatic<T> {}
+
+#[cfg(test)]
+mod tests {
+    use alloc::sync::Arc;
+
+    use crate::{
+        arc::{Align8, MaybeStatic, StaticRef},
+        atomic::AtomicArcPtr,
+        domain,
+        write_policy::Concurrent,
+        AtomicMaybeStatic,
+    };
+
+    #[test]
+    fn static_ref() {
+        domain!(TestDomain(1));
+        static FOO: u32 = 0;
+        static BAR: u32 = 1;
+        let atomic_ref = AtomicArcPtr::<_, TestDomain, Concurrent>::new(StaticRef(&FOO));
+        let borrow = atomic_ref.load();
+        assert_eq!(*atomic_ref.swap(StaticRef(&BAR)), 0);
+        assert_eq!(**borrow, 0);
+        assert!(atomic_ref
+            .compare_exchange(&*borrow, StaticRef(&FOO))
+            .is_err());
+        assert_eq!(**atomic_ref.load(), 1);
+    }
+
+    #[test]
+    fn maybe_static() {
+        domain!(TestDomain(1));
+        static DEFAULT: Align8<u32> = Align8(0);
+        let atomic_arc = AtomicMaybeStatic::<u32, TestDomain>::new((&DEFAULT).into());
+        let borrow = atomic_arc.load();
+        assert!(matches!(*borrow, MaybeStatic::Static(_)));
+        let arc = Arc::new(1);
+        atomic_arc.store(arc.clone().into());
+        assert_eq!(**borrow, 0);
+        drop(borrow);
+        let borrow = atomic_arc.load();
+        assert!(matches!(&*borrow, MaybeStatic::Arc(a) if Arc::ptr_eq(a, &arc)));
+        assert_eq!(Arc::strong_count(&arc), 2);
+        atomic_arc.store(MaybeStatic::Static(&DEFAULT));
+        assert_eq!(**borrow, 1);
+        drop(borrow);
+        assert_eq!(Arc::strong_count(&arc), 1);
+        assert_eq!(**atomic_arc.load(), 0);
+    }
+}
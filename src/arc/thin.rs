@@ -0,0 +1,324 @@
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::{
+    borrow::Borrow,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    mem::ManuallyDrop,
+    ops::Deref,
+    ptr,
+    ptr::NonNull,
+    sync::atomic::{
+        fence, AtomicUsize,
+        Ordering::{Acquire, Relaxed, Release},
+    },
+};
+
+use crate::{
+    arc::{ArcPtr, NonNullArcPtr},
+    domain::abort_before_overflow,
+};
+
+struct ThinArcHeader<T: ?Sized> {
+    count: AtomicUsize,
+    // (fat) pointer to the value, keeping its original metadata
+    value: *mut T,
+    // deallocates the header and the value, which may be stored inline or boxed
+    drop: unsafe fn(*mut ThinArcHeader<T>),
+}
+
+// sized values are stored inline, after the header
+#[repr(C)]
+struct ThinArcInline<T> {
+    header: ThinArcHeader<T>,
+    value: T,
+}
+
+unsafe fn drop_inline<T>(ptr: *mut ThinArcHeader<T>) {
+    drop(unsafe { Box::from_raw(ptr.cast::<ThinArcInline<T>>()) });
+}
+
+unsafe fn drop_boxed<T: ?Sized>(ptr: *mut ThinArcHeader<T>) {
+    let header = unsafe { Box::from_raw(ptr) };
+    drop(unsafe { Box::from_raw(header.value) });
+}
+
+/// A thread-safe reference-counted pointer, whose pointer is thin even for dynamically sized
+/// types.
+///
+/// [`Arc<T>`](alloc::sync::Arc) implements [`ArcPtr`] only for sized `T`, as `AtomicArc`
+/// stores a single thin pointer. `ThinArc` stores the pointer metadata in its allocation
+/// header, so it can be used with `str`, `[T]` or `dyn Trait`.
+///
+/// Sized values are stored inline after the header, in a single allocation. Unsized values are
+/// however kept in the allocation they are converted from, e.g. a `Box` or a `String`, as a
+/// fat pointer cannot be rebuilt with the same metadata and another address on stable Rust;
+/// the header is then allocated separately.
+///
+/// # Examples
+///
+/// ```rust
+/// # use std::fmt::Display;
+/// # use hazarc::arc::ThinArc;
+/// # hazarc::domain!(Domain(8));
+/// # type AtomicThinArc<T> = hazarc::AtomicThinArc<T, Domain>;
+/// let atomic_str = AtomicThinArc::<str>::new(ThinArc::from("foo"));
+/// assert_eq!(&**atomic_str.load(), "foo");
+///
+/// let atomic_dyn =
+///     AtomicThinArc::<dyn Display>::new(ThinArc::from(Box::new(42) as Box<dyn Display>));
+/// assert_eq!(atomic_dyn.load().to_string(), "42");
+/// ```
+pub struct ThinArc<T: ?Sized> {
+    ptr: NonNull<ThinArcHeader<T>>,
+    _value: PhantomData<T>,
+}
+
+unsafe impl<T: ?Sized + Send + Sync> Send for ThinArc<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for ThinArc<T> {}
+
+impl<T> ThinArc<T> {
+    /// Constructs a new `ThinArc<T>`, storing the value inline.
+    pub fn new(value: T) -> Self {
+        let inline = Box::into_raw(Box::new(ThinArcInline {
+            header: ThinArcHeader {
+                count: AtomicUsize::new(1),
+                value: ptr::null_mut(),
+                drop: drop_inline::<T>,
+            },
+            value,
+        }));
+        unsafe { (*inline).header.value = ptr::addr_of_mut!((*inline).value) };
+        Self {
+            ptr: unsafe { NonNull::new_unchecked(inline.cast()) },
+            _value: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized> ThinArc<T> {
+    fn header(&self) -> &ThinArcHeader<T> {
+        unsafe { self.ptr.as_ref() }
+    }
+
+    /// Returns `true` if the two `ThinArc`s point to the same allocation.
+    #[inline]
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.ptr == other.ptr
+    }
+
+    /// Gets the number of strong pointers to this allocation.
+    #[inline]
+    pub fn strong_count(this: &Self) -> usize {
+        this.header().count.load(Relaxed)
+    }
+
+    #[cold]
+    #[inline(never)]
+    unsafe fn drop_slow(&mut self) {
+        unsafe { (self.header().drop)(self.ptr.as_ptr()) };
+    }
+}
+
+impl<T: ?Sized> Clone for ThinArc<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        let count = self.header().count.fetch_add(1, Relaxed);
+        abort_before_overflow(count, "too many references");
+        Self {
+            ptr: self.ptr,
+            _value: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized> Drop for ThinArc<T> {
+    #[inline]
+    fn drop(&mut self) {
+        if self.header().count.fetch_sub(1, Release) == 1 {
+            fence(Acquire);
+            unsafe { self.drop_slow() };
+        }
+    }
+}
+
+impl<T: ?Sized> Deref for ThinArc<T> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.header().value }
+    }
+}
+
+impl<T: ?Sized> AsRef<T> for ThinArc<T> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T: ?Sized> Borrow<T> for ThinArc<T> {
+    fn borrow(&self) -> &T {
+        self
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for ThinArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T: ?Sized + fmt::Display> fmt::Display for ThinArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T: ?Sized + PartialEq> PartialEq for ThinArc<T> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: ?Sized + Eq> Eq for ThinArc<T> {}
+
+impl<T: ?Sized + Hash> Hash for ThinArc<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state);
+    }
+}
+
+impl<T: Default> Default for ThinArc<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> From<T> for ThinArc<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: ?Sized> From<Box<T>> for ThinArc<T> {
+    fn from(value: Box<T>) -> Self {
+        // the value stays in its box allocation, as a fat pointer cannot be rebuilt with the
+        // same metadata and another address on stable Rust
+        let header = Box::new(ThinArcHeader {
+            count: AtomicUsize::new(1),
+            value: Box::into_raw(value),
+            drop: drop_boxed::<T>,
+        });
+        Self {
+            ptr: unsafe { NonNull::new_unchecked(Box::into_raw(header)) },
+            _value: PhantomData,
+        }
+    }
+}
+
+impl From<&str> for ThinArc<str> {
+    fn from(value: &str) -> Self {
+        Box::<str>::from(value).into()
+    }
+}
+
+impl From<String> for ThinArc<str> {
+    fn from(value: String) -> Self {
+        value.into_boxed_str().into()
+    }
+}
+
+impl<T: Clone> From<&[T]> for ThinArc<[T]> {
+    fn from(value: &[T]) -> Self {
+        Box::<[T]>::from(value).into()
+    }
+}
+
+impl<T> From<Vec<T>> for ThinArc<[T]> {
+    fn from(value: Vec<T>) -> Self {
+        value.into_boxed_slice().into()
+    }
+}
+
+#[cfg(not(target_pointer_width = "16"))]
+unsafe impl<T: ?Sized> ArcPtr for ThinArc<T> {
+    #[inline(always)]
+    unsafe fn from_ptr(ptr: *mut ()) -> Self {
+        Self {
+            ptr: unsafe { NonNull::new_unchecked(ptr.cast()) },
+            _value: PhantomData,
+        }
+    }
+    #[inline(always)]
+    fn into_ptr(arc: Self) -> *mut () {
+        ManuallyDrop::new(arc).ptr.as_ptr().cast()
+    }
+    #[inline(always)]
+    fn as_ptr(arc: &Self) -> *mut () {
+        arc.ptr.as_ptr().cast()
+    }
+}
+
+#[cfg(not(target_pointer_width = "16"))]
+unsafe impl<T: ?Sized> NonNullArcPtr for ThinArc<T> {}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{boxed::Box, string::ToString, sync::Arc};
+    use core::{
+        fmt,
+        sync::atomic::{AtomicUsize, Ordering::Relaxed},
+    };
+
+    use crate::{arc::ThinArc, domain, AtomicThinArc, Cache};
+
+    #[test]
+    fn unsized_atomic_arc() {
+        domain!(TestDomain(1));
+        let atomic_arc = AtomicThinArc::<str, TestDomain>::new("foo".into());
+        assert_eq!(&**atomic_arc.load(), "foo");
+        let foo = atomic_arc.swap("bar".into());
+        assert_eq!(&*foo, "foo");
+        assert!(atomic_arc.compare_exchange(&foo, "baz".into()).is_err());
+        let bar = atomic_arc.load_owned();
+        assert_eq!(
+            &*atomic_arc.compare_exchange(&bar, "baz".into()).unwrap(),
+            "bar"
+        );
+        let mut cache = Cache::new(Arc::new(atomic_arc));
+        assert_eq!(&**cache.load(), "baz");
+        cache.inner().store(ThinArc::from("qux".to_string()));
+        assert_eq!(&**cache.load(), "qux");
+    }
+
+    #[test]
+    fn dyn_drop() {
+        domain!(TestDomain(1));
+        static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+        struct Dropped;
+        impl Drop for Dropped {
+            fn drop(&mut self) {
+                DROP_COUNT.fetch_add(1, Relaxed);
+            }
+        }
+        impl fmt::Display for Dropped {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "dropped")
+            }
+        }
+        let arc: ThinArc<dyn fmt::Display> = (Box::new(Dropped) as Box<dyn fmt::Display>).into();
+        let atomic_arc = AtomicThinArc::<dyn fmt::Display, TestDomain>::new(arc);
+        let borrow = atomic_arc.load();
+        atomic_arc.store(ThinArc::from(Box::new(0) as Box<dyn fmt::Display>));
+        assert_eq!(borrow.to_string(), "dropped");
+        assert_eq!(DROP_COUNT.load(Relaxed), 0);
+        drop(borrow);
+        assert_eq!(DROP_COUNT.load(Relaxed), 1);
+        let arc = ThinArc::new(Dropped);
+        assert_eq!(arc.to_string(), "dropped");
+        drop(arc.clone());
+        assert_eq!(DROP_COUNT.load(Relaxed), 1);
+        drop(arc);
+        assert_eq!(DROP_COUNT.load(Relaxed), 2);
+    }
+}
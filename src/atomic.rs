@@ -1,25 +1,36 @@
 //! Generic atomic storage for `Arc`-like pointers.
 
-use alloc::sync::Arc;
+use alloc::{sync::Arc, vec::Vec};
 use core::{
-    fmt, hint,
+    convert::Infallible,
+    fmt,
+    future::Future,
+    hint,
     marker::PhantomData,
     mem,
     mem::ManuallyDrop,
-    ops::Deref,
+    ops::{Deref, DerefMut},
+    pin::Pin,
     sync::atomic::{
-        AtomicPtr,
+        AtomicPtr, Ordering,
         Ordering::{Acquire, Relaxed, SeqCst},
     },
+    task::{Context, Poll},
 };
 
+use crossbeam_utils::Backoff;
+
+#[cfg(feature = "std")]
+use crate::collector::Collector;
 #[allow(unused_imports)]
 use crate::msrv::{OptionExt, StrictProvenance};
 use crate::{
     arc::{ArcPtr, ArcRef, NonNullArcPtr},
     domain::{BorrowSlot, Domain, DomainNodeRef},
     msrv::ptr,
-    write_policy::{Concurrent, WritePolicy},
+    notify,
+    notify::{Changed, Changes},
+    write_policy::WritePolicy,
     NULL,
 };
 
@@ -27,13 +38,27 @@ const PREPARE_CLONE_FLAG: usize = 0b01;
 const CONFIRM_CLONE_FLAG: usize = 0b10;
 const GENERATION_INCR: usize = PREPARE_CLONE_FLAG + 1;
 const MAX_GENERATION: usize = !PREPARE_CLONE_FLAG;
+// `ArcPtr` pointers are aligned to 4, so clone flags bits can be used to tag the stored pointer
+pub(crate) const TAG_MASK: usize = PREPARE_CLONE_FLAG | CONFIRM_CLONE_FLAG;
+
+fn transfer_ownership<A: ArcPtr>(
+    ptr: *mut (),
+    op: impl FnOnce() -> Result<*mut (), *mut ()>,
+) -> Result<*mut (), *mut ()> {
+    unsafe { A::incr_rc(ptr) };
+    let res = op();
+    if res.is_err() {
+        unsafe { A::decr_rc(ptr) };
+    }
+    res
+}
 
 /// An atomic storage for generic `Arc`-like pointers.
 ///
 /// All atomic operations are [sequentially consistent](SeqCst) and
 /// [pseudo wait-free](crate#wait-freedom).
 pub struct AtomicArcPtr<A: ArcPtr, D: Domain, W: WritePolicy> {
-    ptr: AtomicPtr<()>,
+    pub(crate) ptr: AtomicPtr<()>,
     _arc: PhantomData<A>,
     _domain: PhantomData<D>,
     _write_policy: PhantomData<W>,
@@ -54,11 +79,28 @@ impl<A: ArcPtr, D: Domain, W: WritePolicy> AtomicArcPtr<A, D, W> {
     /// Loads a borrowed Arc.
     #[inline]
     pub fn load(&self) -> ArcPtrBorrow<A> {
-        self.load_impl(self.ptr.load(if A::NULLABLE { SeqCst } else { Relaxed }))
+        self.load_impl(self.load_ptr(if A::NULLABLE { SeqCst } else { Relaxed }))
+    }
+
+    /// Loads the stored pointer, stripping the tag set by
+    /// [`TaggedAtomicArcPtr`](crate::tagged::TaggedAtomicArcPtr).
+    #[inline(always)]
+    pub(crate) fn load_ptr(&self, ordering: Ordering) -> *mut () {
+        Self::untagged(self.ptr.load(ordering))
+    }
+
+    /// Strips the tag of the pointer; it is a no-op for untagged storages.
+    #[inline(always)]
+    #[allow(unstable_name_collisions)]
+    fn untagged(ptr: *mut ()) -> *mut () {
+        if W::TAG_MASK == 0 {
+            return ptr;
+        }
+        ptr.map_addr(|addr| addr & !W::TAG_MASK)
     }
 
     #[inline(always)]
-    fn load_impl(&self, ptr: *mut ()) -> ArcPtrBorrow<A> {
+    pub(crate) fn load_impl(&self, ptr: *mut ()) -> ArcPtrBorrow<A> {
         if A::NULLABLE && ptr.is_null() {
             return ArcPtrBorrow::new(NULL, None);
         }
@@ -86,7 +128,7 @@ impl<A: ArcPtr, D: Domain, W: WritePolicy> AtomicArcPtr<A, D, W> {
         slot_idx: usize,
     ) -> ArcPtrBorrow<A> {
         slot.store(ptr, SeqCst);
-        let ptr_checked = self.ptr.load(SeqCst);
+        let ptr_checked = self.load_ptr(SeqCst);
         if ptr != ptr_checked {
             return self.load_outdated(ptr, node, ptr_checked, slot);
         }
@@ -163,7 +205,7 @@ impl<A: ArcPtr, D: Domain, W: WritePolicy> AtomicArcPtr<A, D, W> {
             self_ptr.map_addr(|addr| addr | PREPARE_CLONE_FLAG)
         };
         clone_slot.store(prepare_ptr, SeqCst);
-        let ptr_checked = self.ptr.load(SeqCst);
+        let ptr_checked = self.load_ptr(SeqCst);
         if A::NULLABLE && ptr_checked.is_null() {
             let ptr = clone_slot.swap(NULL, SeqCst);
             if ptr != prepare_ptr {
@@ -199,7 +241,7 @@ impl<A: ArcPtr, D: Domain, W: WritePolicy> AtomicArcPtr<A, D, W> {
     /// Returns a reference to the cached Arc if it is up-to-date, or loads the latest Arc.
     #[inline]
     pub fn load_cached_or_reload<'a>(&self, cached: &'a A) -> CachedOrReloaded<'a, A> {
-        let ptr = self.ptr.load(SeqCst);
+        let ptr = self.load_ptr(SeqCst);
         if ptr == A::as_ptr(cached) {
             CachedOrReloaded::Cached(cached)
         } else {
@@ -218,7 +260,7 @@ impl<A: ArcPtr, D: Domain, W: WritePolicy> AtomicArcPtr<A, D, W> {
     /// See [`Cache`](crate::Cache) for a convenient wrapper around this method.
     #[inline]
     pub fn load_cached<'a>(&self, cached: &'a mut A) -> &'a A {
-        let ptr = self.ptr.load(SeqCst);
+        let ptr = self.load_ptr(SeqCst);
         if ptr != A::as_ptr(cached) {
             *cached = self.reload_cache(ptr);
         }
@@ -232,75 +274,91 @@ impl<A: ArcPtr, D: Domain, W: WritePolicy> AtomicArcPtr<A, D, W> {
         self.swap_impl(old_ptr, Some(arc))
     }
 
-    #[allow(unstable_name_collisions)]
-    fn swap_impl(&self, old_ptr: *mut (), mut new: Option<A>) -> A {
-        fn transfer_ownership<A: ArcPtr>(
-            ptr: *mut (),
-            op: impl FnOnce() -> Result<*mut (), *mut ()>,
-        ) -> Result<*mut (), *mut ()> {
-            unsafe { A::incr_rc(ptr) };
-            let res = op();
-            if res.is_err() {
-                unsafe { A::decr_rc(ptr) };
-            }
-            res
+    pub(crate) fn swap_impl(&self, old_ptr: *mut (), mut new: Option<A>) -> A {
+        if new.is_some() {
+            notify::notify(self.addr());
         }
         let old_arc = unsafe { A::from_ptr(old_ptr) };
+        // Borrows of a static pointer don't need to be protected, and a concurrent clone
+        // of a static pointer stays valid, so there is nothing to transfer
+        if A::is_static(old_ptr) {
+            return old_arc;
+        }
         for node in D::static_list().nodes() {
             #[cfg(any(
                 not(target_pointer_width = "64"),
                 hazarc_force_active_writer_count_64bit
             ))]
             let _guard = W::CONCURRENT.then(|| node.writer_guard());
-            if !A::NULLABLE || !old_ptr.is_null() {
-                for slot in node.borrow_slots().iter() {
-                    if slot.load(SeqCst) == old_ptr {
-                        let _ = transfer_ownership::<A>(old_ptr, || {
-                            #[cfg(feature = "domain-gc")]
-                            let reset = ptr::without_provenance_mut(1);
-                            #[cfg(not(feature = "domain-gc"))]
-                            let reset = NULL;
-                            // Acquire failure so borrow happens before
-                            slot.compare_exchange(old_ptr, reset, SeqCst, Acquire)
-                        });
-                    }
+            self.transfer_node_ownership(node, old_ptr, &mut new);
+        }
+        old_arc
+    }
+
+    /// Transfers the ownership of the swapped Arcs to the borrow and clone slots of the node.
+    ///
+    /// The old pointer must not be static, and the node writer guard must be held with
+    /// [`Concurrent`](crate::write_policy::Concurrent) write policy on platforms requiring it.
+    #[allow(unstable_name_collisions)]
+    pub(crate) fn transfer_node_ownership(
+        &self,
+        node: DomainNodeRef<D>,
+        old_ptr: *mut (),
+        new: &mut Option<A>,
+    ) {
+        if !A::NULLABLE || !old_ptr.is_null() {
+            for slot in node.borrow_slots().iter() {
+                if slot.load(SeqCst) == old_ptr {
+                    let _ = transfer_ownership::<A>(old_ptr, || {
+                        #[cfg(feature = "domain-gc")]
+                        let reset = ptr::without_provenance_mut(1);
+                        #[cfg(not(feature = "domain-gc"))]
+                        let reset = NULL;
+                        // Acquire failure so borrow happens before
+                        slot.compare_exchange(old_ptr, reset, SeqCst, Acquire)
+                    });
                 }
             }
-            let Some(mut new_ptr) = new.as_ref().map(A::as_ptr) else {
-                continue;
-            };
-            let clone_slot = node.clone_slot();
-            let mut clone_ptr = clone_slot.load(SeqCst);
-            if clone_ptr.addr() & (PREPARE_CLONE_FLAG | CONFIRM_CLONE_FLAG) == 0 {
-                continue;
-            }
-            if clone_ptr.addr() & PREPARE_CLONE_FLAG != 0
-                && self.is_same_atomic_arc(node, &mut clone_ptr)
-            {
-                if W::CONCURRENT {
-                    // Reload the arc if it is outdated to avoid non-monotonic loads,
-                    // as this swap execution could be late, and a previous load of
-                    // this node's thread could have loaded the value arc of a subsequent swap
-                    let ptr_checked = self.ptr.load(SeqCst);
-                    if ptr_checked != new_ptr {
-                        let arc = self.load_impl(ptr_checked).into_owned();
-                        new_ptr = A::as_ptr(&arc);
-                        new = Some(arc);
-                    }
-                }
-                if let Err(p) = transfer_ownership::<A>(new_ptr, || {
-                    clone_slot.compare_exchange(clone_ptr, new_ptr, SeqCst, Relaxed)
-                }) {
-                    clone_ptr = p;
+        }
+        let Some(mut new_ptr) = new.as_ref().map(A::as_ptr) else {
+            return;
+        };
+        let clone_slot = node.clone_slot();
+        let mut clone_ptr = clone_slot.load(SeqCst);
+        if clone_ptr.addr() & (PREPARE_CLONE_FLAG | CONFIRM_CLONE_FLAG) == 0 {
+            return;
+        }
+        if clone_ptr.addr() & PREPARE_CLONE_FLAG != 0
+            && self.is_same_atomic_arc(node, &mut clone_ptr)
+        {
+            if W::CONCURRENT {
+                // Reload the arc if it is outdated to avoid non-monotonic loads,
+                // as this swap execution could be late, and a previous load of
+                // this node's thread could have loaded the value arc of a subsequent swap
+                let ptr_checked = self.load_ptr(SeqCst);
+                if ptr_checked != new_ptr {
+                    let arc = self.load_impl(ptr_checked).into_owned();
+                    new_ptr = A::as_ptr(&arc);
+                    *new = Some(arc);
                 }
             }
-            if clone_ptr.addr() == old_ptr.addr() | CONFIRM_CLONE_FLAG {
-                let _ = transfer_ownership::<A>(old_ptr, || {
-                    clone_slot.compare_exchange(clone_ptr, NULL, SeqCst, Relaxed)
-                });
+            if let Err(p) = transfer_ownership::<A>(new_ptr, || {
+                clone_slot.compare_exchange(clone_ptr, new_ptr, SeqCst, Relaxed)
+            }) {
+                clone_ptr = p;
             }
         }
-        old_arc
+        if clone_ptr.addr() == old_ptr.addr() | CONFIRM_CLONE_FLAG {
+            let _ = transfer_ownership::<A>(old_ptr, || {
+                clone_slot.compare_exchange(clone_ptr, NULL, SeqCst, Relaxed)
+            });
+        }
+    }
+
+    #[allow(unstable_name_collisions)]
+    #[inline(always)]
+    pub(crate) fn addr(&self) -> usize {
+        ptr::from_ref(&self.ptr).addr()
     }
 
     #[allow(unstable_name_collisions)]
@@ -322,30 +380,17 @@ impl<A: ArcPtr, D: Domain, W: WritePolicy> AtomicArcPtr<A, D, W> {
         drop(self.swap(arc));
     }
 
-    /// # Safety
-    ///
-    /// `self` must not be reused after.
-    #[inline(always)]
-    unsafe fn take_owned(&mut self) -> A {
-        let ptr = *self.ptr.get_mut();
-        if A::NULLABLE && ptr.is_null() {
-            return unsafe { A::from_ptr(NULL) };
-        }
-        self.swap_impl(ptr, None)
-    }
-
-    /// Consumes the atomic storage and returns the stored Arc.
-    #[inline]
-    pub fn into_owned(self) -> A {
-        // SAFETY: self is not reused after
-        unsafe { ManuallyDrop::new(self).take_owned() }
-    }
-}
-
-impl<A: ArcPtr, D: Domain> AtomicArcPtr<A, D, Concurrent> {
     /// Stores the new Arc if the current one matches the argument.
     ///
     /// Returns the previous Arc if store succeeds, or loads the current Arc otherwise.
+    ///
+    /// The comparison is made on the stored pointer, so it is always exact. However, with
+    /// [`Serialized`] write policy, concurrent writes can provoke non-monotonic reads: the
+    /// loaded Arc returned on failure may then be older than the one which made the comparison
+    /// fail. It is not the case if writes are actually serialized, e.g. with a single writer
+    /// conditionally storing an Arc it has previously loaded.
+    ///
+    /// [`Serialized`]: crate::write_policy::Serialized
     pub fn compare_exchange<C: ArcRef<A>>(&self, current: C, new: A) -> Result<A, ArcPtrBorrow<A>> {
         // store a clone in order to keep an owned arc, in case its ownership must be transferred
         let new_clone = A::into_ptr(new.clone());
@@ -362,6 +407,9 @@ impl<A: ArcPtr, D: Domain> AtomicArcPtr<A, D, Concurrent> {
     /// the current Arc has not changed.
     ///
     /// Returns `Err` with the current Arc if the function returns `None`.
+    ///
+    /// See [`compare_exchange`](Self::compare_exchange) for the interaction with
+    /// [`Serialized`](crate::write_policy::Serialized) write policy.
     pub fn fetch_update<F: FnMut(&A) -> Option<R>, R: Into<A>>(
         &self,
         mut f: F,
@@ -375,6 +423,274 @@ impl<A: ArcPtr, D: Domain> AtomicArcPtr<A, D, Concurrent> {
         }
         Err(current)
     }
+
+    /// # Safety
+    ///
+    /// `self` must not be reused after.
+    #[inline(always)]
+    unsafe fn take_owned(&mut self) -> A {
+        let ptr = Self::untagged(*self.ptr.get_mut());
+        if A::NULLABLE && ptr.is_null() {
+            return unsafe { A::from_ptr(NULL) };
+        }
+        self.swap_impl(ptr, None)
+    }
+
+    /// Consumes the atomic storage and returns the stored Arc.
+    #[inline]
+    pub fn into_owned(self) -> A {
+        // SAFETY: self is not reused after
+        unsafe { ManuallyDrop::new(self).take_owned() }
+    }
+
+    /// Consumes the atomic storages and returns the stored Arcs.
+    ///
+    /// Contrary to calling [`into_owned`](Self::into_owned) on each storage, the ownership of
+    /// all the Arcs is transferred with a single pass over the domain nodes.
+    pub fn into_owned_all(atomic_arcs: impl IntoIterator<Item = Self>) -> Vec<A> {
+        let ptrs: Vec<_> = (atomic_arcs.into_iter())
+            .map(|atomic_arc| Self::untagged(*ManuallyDrop::new(atomic_arc).ptr.get_mut()))
+            .collect();
+        // Borrows of a static pointer don't need to be protected, see `swap_impl`
+        let mut old_ptrs: Vec<_> = (ptrs.iter().copied())
+            .filter(|ptr| !(A::NULLABLE && ptr.is_null() || A::is_static(*ptr)))
+            .collect();
+        old_ptrs.sort_unstable();
+        old_ptrs.dedup();
+        if !old_ptrs.is_empty() {
+            Self::transfer_ownership_all(&old_ptrs);
+        }
+        (ptrs.into_iter())
+            .map(|ptr| unsafe { A::from_ptr(ptr) })
+            .collect()
+    }
+
+    /// Drops the atomic storages, with a single pass over the domain nodes.
+    ///
+    /// See [`into_owned_all`](Self::into_owned_all).
+    pub fn drop_all(atomic_arcs: impl IntoIterator<Item = Self>) {
+        drop(Self::into_owned_all(atomic_arcs));
+    }
+
+    /// Transfers the ownership of the sorted old pointers to the borrow and clone slots.
+    ///
+    /// Storages are consumed, so there is no concurrent load to handle, only the borrows and
+    /// clones made before.
+    #[allow(unstable_name_collisions)]
+    fn transfer_ownership_all(old_ptrs: &[*mut ()]) {
+        let is_old_ptr = |ptr| old_ptrs.binary_search(&ptr).is_ok();
+        for node in D::static_list().nodes() {
+            #[cfg(any(
+                not(target_pointer_width = "64"),
+                hazarc_force_active_writer_count_64bit
+            ))]
+            let _guard = W::CONCURRENT.then(|| node.writer_guard());
+            for slot in node.borrow_slots().iter() {
+                let ptr = slot.load(SeqCst);
+                if is_old_ptr(ptr) {
+                    let _ = transfer_ownership::<A>(ptr, || {
+                        #[cfg(feature = "domain-gc")]
+                        let reset = ptr::without_provenance_mut(1);
+                        #[cfg(not(feature = "domain-gc"))]
+                        let reset = NULL;
+                        // Acquire failure so borrow happens before
+                        slot.compare_exchange(ptr, reset, SeqCst, Acquire)
+                    });
+                }
+            }
+            let clone_slot = node.clone_slot();
+            let clone_ptr = clone_slot.load(SeqCst);
+            if clone_ptr.addr() & (PREPARE_CLONE_FLAG | CONFIRM_CLONE_FLAG) != CONFIRM_CLONE_FLAG {
+                continue;
+            }
+            let old_ptr = clone_ptr.map_addr(|addr| addr & !CONFIRM_CLONE_FLAG);
+            if is_old_ptr(old_ptr) {
+                let _ = transfer_ownership::<A>(old_ptr, || {
+                    clone_slot.compare_exchange(clone_ptr, NULL, SeqCst, Relaxed)
+                });
+            }
+        }
+    }
+}
+
+impl<A: ArcPtr, D: Domain, W: WritePolicy> AtomicArcPtr<A, D, W> {
+    /// Returns a future which completes when the stored Arc differs from `seen`, returning the
+    /// new one.
+    ///
+    /// The waker is registered in a global registry, sharded by storage address, and woken by
+    /// the next write — [`swap`](Self::swap), [`store`](Self::store), `compare_exchange`, etc.
+    /// Writes only pay for notification when there are registered wakers for storages sharing
+    /// the same shard, and reads are not impacted at all.
+    /// It doesn't require `std`, so it can be used with custom domains.
+    ///
+    /// The future holds a clone of `seen`, so its address cannot be reused by a new Arc, which
+    /// would then not be detected as a change.
+    pub fn changed<C: ArcRef<A>>(&self, seen: C) -> Changed<'_, A, D, W> {
+        let seen = C::as_ptr(seen);
+        // SAFETY: `seen` references a live Arc
+        unsafe { A::incr_rc(seen) };
+        Changed::new(self, unsafe { A::from_ptr(seen) })
+    }
+
+    /// Returns a stream of the Arcs stored after its creation.
+    ///
+    /// See [`changed`](Self::changed).
+    pub fn changes(&self) -> Changes<'_, A, D, W> {
+        Changes::new(self)
+    }
+
+    /// Blocks the current thread until the stored Arc differs from `seen`, and returns the
+    /// new one.
+    ///
+    /// The thread is parked until a write stores a new Arc. See [`changed`](Self::changed).
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn wait_changed<C: ArcRef<A>>(&self, seen: C) -> ArcPtrBorrow<A> {
+        notify::block_on(self.changed(seen))
+    }
+
+    /// Blocks the current thread until the stored Arc satisfies the predicate, and returns it.
+    ///
+    /// The predicate is evaluated on the current Arc, and then on each new stored Arc.
+    /// See [`wait_changed`](Self::wait_changed).
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn wait_until<F: FnMut(&A) -> bool>(&self, mut f: F) -> ArcPtrBorrow<A> {
+        let mut arc = self.load();
+        while !f(&arc) {
+            arc = self.wait_changed(&arc);
+        }
+        arc
+    }
+
+    /// Stores the Arc, retiring the previous one into the collector instead of dropping it.
+    ///
+    /// The previous Arc is dropped by a later [`Collector::collect`], so the potential
+    /// deallocation of its value is offloaded from the current thread.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn store_deferred(&self, arc: A, collector: &Collector)
+    where
+        A: Send + 'static,
+    {
+        collector.retire(self.swap(arc));
+    }
+}
+
+impl<A, D: Domain, W: WritePolicy> AtomicArcPtr<A, D, W>
+where
+    A: ArcPtr + Deref,
+    A::Target: PartialEq,
+{
+    /// Stores the Arc if its value differs from the current one, and returns whether it has
+    /// been stored.
+    ///
+    /// Republishing an equal value then costs a load and a comparison, without scanning the
+    /// domain nor notifying waiters, and cached Arcs are not invalidated.
+    pub fn store_if_ne(&self, new: A) -> bool {
+        let mut current = self.load();
+        while **current != *new {
+            match self.compare_exchange(&*current, new.clone()) {
+                Ok(_) => return true,
+                Err(arc) => current = arc,
+            }
+        }
+        false
+    }
+
+    /// Stores the new Arc if the value of the current one equals the expected value.
+    ///
+    /// Returns the previous Arc if store succeeds, or loads the current Arc otherwise.
+    /// Contrary to [`compare_exchange`](Self::compare_exchange), the store succeeds even if the
+    /// current Arc has been replaced by an equal value.
+    pub fn compare_exchange_eq(&self, expected: &A::Target, new: A) -> Result<A, ArcPtrBorrow<A>> {
+        let mut current = self.load();
+        while **current == *expected {
+            match self.compare_exchange(&*current, new.clone()) {
+                Ok(old_arc) => return Ok(old_arc),
+                Err(arc) => current = arc,
+            }
+        }
+        Err(current)
+    }
+}
+
+impl<T, D: Domain, W: WritePolicy> AtomicArcPtr<Arc<T>, D, W> {
+    /// Stores the Arc, and waits until the previous one is no longer borrowed or cloned to
+    /// return its inner value.
+    ///
+    /// The ownership of the previous Arc is transferred to its outstanding borrows when it is
+    /// swapped, so it becomes unique once all of them, and all its clones, are dropped. Their
+    /// release is not signaled, so this method is a spin loop (with backoff) until then; it
+    /// should only be used when borrows are expected to be released soon, and it never returns
+    /// if the current thread holds one of them.
+    ///
+    /// See [`swap_and_synchronize_async`](Self::swap_and_synchronize_async) for an async
+    /// version.
+    pub fn swap_and_synchronize(&self, arc: Arc<T>) -> T {
+        let mut old_arc = self.swap(arc);
+        let backoff = Backoff::new();
+        loop {
+            match Arc::try_unwrap(old_arc) {
+                Ok(value) => return value,
+                Err(arc) => old_arc = arc,
+            }
+            backoff.snooze();
+        }
+    }
+
+    /// Stores the Arc, and returns a future which completes when the previous one is no
+    /// longer borrowed or cloned, returning its inner value.
+    ///
+    /// As for [`swap_and_synchronize`](Self::swap_and_synchronize), the release is not
+    /// signaled, so the future checks it each time it is polled, and wakes itself immediately
+    /// if the previous Arc is still shared. It thus yields to the executor between checks,
+    /// letting other tasks — including the ones of the current thread holding a borrow — make
+    /// progress, but it keeps being polled until completion, so it should only be used when
+    /// borrows are expected to be released soon.
+    pub fn swap_and_synchronize_async(&self, arc: Arc<T>) -> Synchronize<T> {
+        Synchronize(Some(self.swap(arc)))
+    }
+
+    /// Clones the current value, applies the mutation on it, stores the result and returns
+    /// the previous Arc.
+    ///
+    /// With [`Concurrent`](crate::write_policy::Concurrent) write policy, the result is stored
+    /// with a compare-and-swap, and the mutation is applied again on the new current value if it
+    /// fails. Otherwise, writes are assumed to be serialized, so the result is simply swapped.
+    pub fn update_mut<F: FnMut(&mut T)>(&self, mut f: F) -> Arc<T>
+    where
+        T: Clone,
+    {
+        let mut current = self.load();
+        loop {
+            let mut value = T::clone(&current);
+            f(&mut value);
+            if !W::CONCURRENT {
+                return self.swap(Arc::new(value));
+            }
+            match self.compare_exchange(&*current, Arc::new(value)) {
+                Ok(old_arc) => return old_arc,
+                Err(arc) => current = arc,
+            }
+        }
+    }
+
+    /// Clones the current value into a guard which can be mutated, and stores it when dropped.
+    ///
+    /// See [`Edit`].
+    pub fn edit(&self) -> Edit<'_, T, D, W>
+    where
+        T: Clone,
+    {
+        let current = self.load();
+        let value = Some(T::clone(&current));
+        Edit {
+            atomic_arc: self,
+            current,
+            value,
+        }
+    }
 }
 
 impl<A: NonNullArcPtr, D: Domain, W: WritePolicy> AtomicArcPtr<Option<A>, D, W> {
@@ -392,7 +708,7 @@ impl<A: NonNullArcPtr, D: Domain, W: WritePolicy> AtomicArcPtr<Option<A>, D, W>
     /// Returns `true` if the stored Arc is `None`.
     #[inline]
     pub fn is_none(&self) -> bool {
-        self.ptr.load(SeqCst).is_null()
+        self.load_ptr(SeqCst).is_null()
     }
 }
 
@@ -549,6 +865,120 @@ impl<A: NonNullArcPtr> From<Option<ArcPtrBorrow<A>>> for ArcPtrBorrow<Option<A>>
     }
 }
 
+/// Future returned by [`AtomicArcPtr::swap_and_synchronize_async`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Synchronize<T>(Option<Arc<T>>);
+
+impl<T> Future for Synchronize<T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let arc = self.0.take().expect("polled after completion");
+        match Arc::try_unwrap(arc) {
+            Ok(value) => Poll::Ready(value),
+            Err(arc) => {
+                self.0 = Some(arc);
+                // yield to the executor, as the release is not signaled
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Guard returned by [`AtomicArcPtr::edit`], dereferencing to a mutable copy of the edited
+/// value.
+///
+/// The copy is stored when the guard is dropped or [published](Self::publish), unless it is
+/// [aborted](Self::abort). With [`Concurrent`](crate::write_policy::Concurrent) write policy,
+/// it is stored with a compare-and-swap, so it fails if another write happened in the meantime;
+/// the edit is then discarded. Dropping the guard doesn't report it, so `publish` must be used
+/// to detect it, or [`update_mut`](AtomicArcPtr::update_mut) to retry the edit instead.
+/// Otherwise, writes are assumed to be serialized, so the copy is simply swapped.
+///
+/// With `std` feature, the edit is discarded if the guard is dropped while the thread is
+/// panicking.
+///
+/// # Examples
+///
+/// ```rust
+/// # hazarc::domain!(Domain(8));
+/// # type AtomicArc<T> = hazarc::AtomicArc<T, Domain>;
+/// let atomic_arc = AtomicArc::<Vec<usize>>::from(vec![0]);
+/// atomic_arc.edit().push(1);
+/// assert_eq!(**atomic_arc.load(), [0, 1]);
+/// let mut edit = atomic_arc.edit();
+/// edit.clear();
+/// edit.abort();
+/// assert_eq!(**atomic_arc.load(), [0, 1]);
+/// ```
+#[must_use = "the edit is stored when dropped"]
+pub struct Edit<'a, T, D: Domain, W: WritePolicy> {
+    atomic_arc: &'a AtomicArcPtr<Arc<T>, D, W>,
+    current: ArcPtrBorrow<Arc<T>>,
+    value: Option<T>,
+}
+
+impl<T, D: Domain, W: WritePolicy> Edit<'_, T, D, W> {
+    /// Stores the edited value, and returns the previous Arc.
+    ///
+    /// Returns the current Arc if the edited one has been replaced in the meantime and the
+    /// write policy is [`Concurrent`](crate::write_policy::Concurrent); the edit is then
+    /// discarded.
+    #[must_use = "the edit is discarded if the result is an error"]
+    pub fn publish(mut self) -> Result<Arc<T>, ArcPtrBorrow<Arc<T>>> {
+        self.publish_impl()
+    }
+
+    fn publish_impl(&mut self) -> Result<Arc<T>, ArcPtrBorrow<Arc<T>>> {
+        let new = Arc::new(self.value.take().unwrap());
+        if !W::CONCURRENT {
+            return Ok(self.atomic_arc.swap(new));
+        }
+        self.atomic_arc.compare_exchange(&*self.current, new)
+    }
+
+    /// Discards the edited value.
+    pub fn abort(mut self) {
+        self.value = None;
+    }
+}
+
+impl<T, D: Domain, W: WritePolicy> Deref for Edit<'_, T, D, W> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.value.as_ref().unwrap()
+    }
+}
+
+impl<T, D: Domain, W: WritePolicy> DerefMut for Edit<'_, T, D, W> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.value.as_mut().unwrap()
+    }
+}
+
+impl<T, D: Domain, W: WritePolicy> Drop for Edit<'_, T, D, W> {
+    fn drop(&mut self) {
+        #[cfg(feature = "std")]
+        if std::thread::panicking() {
+            return;
+        }
+        if self.value.is_some() {
+            // a concurrent write discards the edit, `publish` must be used to detect it
+            let _ = self.publish_impl();
+        }
+    }
+}
+
+impl<T: fmt::Debug, D: Domain, W: WritePolicy> fmt::Debug for Edit<'_, T, D, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Edit").field(&self.value).finish()
+    }
+}
+
 /// Result of [`AtomicArcPtr::load_cached_or_reload`].
 #[derive(Debug)]
 pub enum CachedOrReloaded<'a, A: ArcPtr> {
@@ -636,6 +1066,20 @@ impl<A: NonNullArcPtr, D: Domain, W: WritePolicy> AtomicOptionArcPtr<A, D, W> {
         self.0.into_owned()
     }
 
+    /// Consumes the atomic storages and returns the stored Arcs.
+    ///
+    /// See [`AtomicArcPtr::into_owned_all`].
+    pub fn into_owned_all(atomic_arcs: impl IntoIterator<Item = Self>) -> Vec<Option<A>> {
+        AtomicArcPtr::into_owned_all(atomic_arcs.into_iter().map(|atomic_arc| atomic_arc.0))
+    }
+
+    /// Drops the atomic storages, with a single pass over the domain nodes.
+    ///
+    /// See [`AtomicArcPtr::into_owned_all`].
+    pub fn drop_all(atomic_arcs: impl IntoIterator<Item = Self>) {
+        drop(Self::into_owned_all(atomic_arcs));
+    }
+
     /// Returns a reference to the cached Arc, updating it when it is outdated.
     ///
     /// See [`Cache`](crate::Cache) for a convenient wrapper around this method.
@@ -659,9 +1103,54 @@ impl<A: NonNullArcPtr, D: Domain, W: WritePolicy> AtomicOptionArcPtr<A, D, W> {
     pub fn store(&self, new: Option<A>) {
         self.0.store(new);
     }
-}
 
-impl<A: NonNullArcPtr, D: Domain> AtomicOptionArcPtr<A, D, Concurrent> {
+    /// Returns the stored Arc, initializing it with `f` if it is `None`.
+    ///
+    /// Concurrent initializations race to store their Arc with a compare-and-swap; `f` may thus
+    /// be called by several threads, but every caller gets the stored Arc.
+    #[inline]
+    pub fn get_or_init<F: FnOnce() -> R, R: Into<A>>(&self, f: F) -> ArcPtrBorrow<A> {
+        match self.get_or_try_init(|| Ok::<_, Infallible>(f())) {
+            Ok(arc) => arc,
+            Err(err) => match err {},
+        }
+    }
+
+    /// Returns the stored Arc, initializing it with `f` if it is `None`.
+    ///
+    /// If `f` fails, the error is returned, and the storage is left uninitialized.
+    ///
+    /// See [`get_or_init`](Self::get_or_init).
+    #[inline]
+    pub fn get_or_try_init<F: FnOnce() -> Result<R, E>, R: Into<A>, E>(
+        &self,
+        f: F,
+    ) -> Result<ArcPtrBorrow<A>, E> {
+        match self.load() {
+            Some(arc) => Ok(arc),
+            None => self.try_init(f),
+        }
+    }
+
+    #[cold]
+    #[inline(never)]
+    fn try_init<F: FnOnce() -> Result<R, E>, R: Into<A>, E>(
+        &self,
+        f: F,
+    ) -> Result<ArcPtrBorrow<A>, E> {
+        let arc = f()?.into();
+        loop {
+            match self.0.compare_exchange(None::<&A>, Some(arc.clone())) {
+                Ok(_) => return Ok(arc.into()),
+                Err(current) => {
+                    if let Some(current) = current.transpose() {
+                        return Ok(current);
+                    }
+                }
+            }
+        }
+    }
+
     /// Stores the new Arc if the current one matches the argument.
     ///
     /// Returns the previous Arc if store succeeds, or loads the current Arc otherwise.
@@ -688,6 +1177,27 @@ impl<A: NonNullArcPtr, D: Domain> AtomicOptionArcPtr<A, D, Concurrent> {
             .map_err(ArcPtrBorrow::transpose)
     }
 }
+
+#[cfg(feature = "std")]
+impl<A: NonNullArcPtr, D: Domain, W: WritePolicy> AtomicOptionArcPtr<A, D, W> {
+    /// Blocks the current thread until the stored Arc differs from `seen`, and returns the
+    /// new one.
+    ///
+    /// See [`AtomicArcPtr::wait_changed`].
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn wait_changed<C: ArcRef<Option<A>>>(&self, seen: C) -> Option<ArcPtrBorrow<A>> {
+        self.0.wait_changed(seen).transpose()
+    }
+
+    /// Blocks the current thread until the stored Arc satisfies the predicate, and returns it.
+    ///
+    /// See [`AtomicArcPtr::wait_until`].
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn wait_until<F: FnMut(Option<&A>) -> bool>(&self, mut f: F) -> Option<ArcPtrBorrow<A>> {
+        self.0.wait_until(|arc| f(arc.as_ref())).transpose()
+    }
+}
+
 impl<A: NonNullArcPtr, D: Domain, W: WritePolicy> Default for AtomicOptionArcPtr<A, D, W> {
     fn default() -> Self {
         Self::none()
@@ -0,0 +1,170 @@
+//! Batched writes of several atomic Arc storages sharing a domain.
+//!
+//! Every write must transfer the ownership of the replaced Arc to the domain nodes still
+//! borrowing it, which requires a pass over all the nodes. A [`Batch`] swaps all its pointers
+//! first, and then makes a single pass over the nodes for all the replaced Arcs.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::{fmt, sync::atomic::Ordering::SeqCst};
+
+use crate::{
+    arc::ArcPtr,
+    atomic::AtomicArcPtr,
+    domain::{Domain, DomainNodeRef},
+    notify,
+    write_policy::WritePolicy,
+    NULL,
+};
+
+/// Constructs an empty [`Batch`] of writes in domain `D`.
+///
+/// # Examples
+///
+/// ```rust
+/// # hazarc::domain!(Domain(8));
+/// # type AtomicArc<T> = hazarc::AtomicArc<T, Domain>;
+/// let tenants: Vec<_> = (0..3).map(|_| AtomicArc::<&str>::from("v0")).collect();
+/// let mut batch = hazarc::batch::<Domain>();
+/// for tenant in &tenants {
+///     batch = batch.store(tenant, "v1".into());
+/// }
+/// batch.commit();
+/// assert!(tenants.iter().all(|tenant| **tenant.load() == "v1"));
+/// ```
+#[inline]
+pub fn batch<'a, D: Domain>() -> Batch<'a, D> {
+    Batch { writes: Vec::new() }
+}
+
+trait BatchWrite<D: Domain> {
+    fn addr(&self) -> usize;
+    fn swap(&mut self);
+    fn transfer(&mut self, node: DomainNodeRef<D>);
+    fn finish(self: Box<Self>);
+}
+
+struct Write<'a, A: ArcPtr, D: Domain, W: WritePolicy> {
+    atomic_arc: &'a AtomicArcPtr<A, D, W>,
+    new: Option<A>,
+    old_ptr: *mut (),
+}
+
+impl<A: ArcPtr, D: Domain, W: WritePolicy> BatchWrite<D> for Write<'_, A, D, W> {
+    fn addr(&self) -> usize {
+        self.atomic_arc.addr()
+    }
+
+    fn swap(&mut self) {
+        let new = self.new.as_ref().unwrap();
+        // store a clone to keep an owned arc, see `AtomicArcPtr::swap`
+        self.old_ptr = (self.atomic_arc.ptr).swap(A::into_ptr(new.clone()), SeqCst);
+        notify::notify(self.atomic_arc.addr());
+    }
+
+    fn transfer(&mut self, node: DomainNodeRef<D>) {
+        // there is nothing to transfer for a static pointer, see `AtomicArcPtr::swap_impl`
+        if A::is_static(self.old_ptr) {
+            return;
+        }
+        #[cfg(any(
+            not(target_pointer_width = "64"),
+            hazarc_force_active_writer_count_64bit
+        ))]
+        let _guard = W::CONCURRENT.then(|| node.writer_guard());
+        (self.atomic_arc).transfer_node_ownership(node, self.old_ptr, &mut self.new);
+    }
+
+    fn finish(self: Box<Self>) {
+        drop(unsafe { A::from_ptr(self.old_ptr) });
+    }
+}
+
+/// A batch of writes of atomic Arc storages sharing domain `D`, constructed with [`batch`].
+///
+/// Writes are only applied by [`commit`](Self::commit): all the Arcs are stored, then a single
+/// pass over the domain nodes transfers the ownership of every replaced Arc. Storages are still
+/// written one after the other, so the batch is not atomic, and loads may observe only some of
+/// the writes while it is committed.
+#[must_use]
+pub struct Batch<'a, D: Domain> {
+    writes: Vec<Box<dyn BatchWrite<D> + 'a>>,
+}
+
+impl<'a, D: Domain> Batch<'a, D> {
+    /// Adds the write of the Arc to the batch.
+    ///
+    /// A previous write of the same storage in the batch is replaced.
+    pub fn store<A: ArcPtr + 'a, W: WritePolicy>(
+        mut self,
+        atomic_arc: &'a AtomicArcPtr<A, D, W>,
+        new: A,
+    ) -> Self {
+        self.writes.retain(|w| w.addr() != atomic_arc.addr());
+        self.writes.push(Box::new(Write {
+            atomic_arc,
+            new: Some(new),
+            old_ptr: NULL,
+        }));
+        self
+    }
+
+    /// Returns the number of writes in the batch.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.writes.len()
+    }
+
+    /// Returns `true` if the batch contains no write.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty()
+    }
+
+    /// Applies all the writes of the batch, dropping the replaced Arcs.
+    pub fn commit(mut self) {
+        for write in &mut self.writes {
+            write.swap();
+        }
+        for node in D::static_list().nodes() {
+            for write in &mut self.writes {
+                write.transfer(node);
+            }
+        }
+        self.writes.into_iter().for_each(BatchWrite::finish);
+    }
+}
+
+impl<D: Domain> fmt::Debug for Batch<'_, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Batch")
+            .field("writes", &self.writes.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::sync::Arc;
+
+    use crate::{domain, write_policy::Serialized, AtomicArc, AtomicOptionArc};
+
+    #[test]
+    fn store_batch() {
+        domain!(TestDomain(1));
+        let old = Arc::new(0);
+        let a = AtomicArc::<usize, TestDomain>::new(old.clone());
+        let b = AtomicOptionArc::<usize, TestDomain, Serialized>::none();
+        let borrow = a.load();
+        let batch = crate::batch::<TestDomain>()
+            .store(&a, 1.into())
+            .store(b.inner(), None)
+            .store(b.inner(), Some(2.into()));
+        assert_eq!(batch.len(), 2);
+        batch.commit();
+        assert_eq!((**a.load(), **b.load().unwrap()), (1, 2));
+        // the borrowed Arc ownership has been transferred to the borrow slot
+        assert_eq!((**borrow, Arc::strong_count(&old)), (0, 2));
+        drop(borrow);
+        assert_eq!(Arc::strong_count(&old), 1);
+    }
+}
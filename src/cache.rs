@@ -170,6 +170,10 @@ impl<A: AtomicArcRef> Cache<A> {
         self.inner
     }
 
+    pub(crate) fn cached(&self) -> &A::Owned {
+        &self.cached
+    }
+
     /// Returns a reference the cached Arc, updating it when it is outdated.
     #[inline]
     pub fn load(&mut self) -> A::LoadCached<'_> {
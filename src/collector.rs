@@ -0,0 +1,159 @@
+//! Deferred reclamation of replaced Arcs.
+//!
+//! Dropping the last reference of a large value can be expensive; [`Collector`] allows
+//! offloading it from latency-sensitive writers, e.g. with
+//! [`AtomicArc::store_deferred`](crate::atomic::AtomicArcPtr::store_deferred).
+
+use alloc::{
+    boxed::Box,
+    collections::VecDeque,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::{fmt, time::Duration};
+use std::{
+    sync::{Mutex, MutexGuard},
+    thread,
+    time::Instant,
+};
+
+/// A queue of retired values, dropped when [collected](Self::collect).
+///
+/// Values can be required to stay retired for a minimum grace period before being dropped.
+/// The queue can be drained explicitly, or by a [background thread](Self::spawn_background).
+///
+/// # Examples
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// # use hazarc::collector::Collector;
+/// # hazarc::domain!(Domain(8));
+/// # type AtomicArc<T> = hazarc::AtomicArc<T, Domain>;
+/// let collector = Collector::new();
+/// let atomic_arc = AtomicArc::<Vec<u8>>::from(vec![0; 1 << 20]);
+/// atomic_arc.store_deferred(Arc::new(Vec::new()), &collector);
+/// assert_eq!(collector.pending(), 1);
+/// assert_eq!(collector.collect(), 1);
+/// ```
+pub struct Collector {
+    queue: Mutex<VecDeque<(Instant, Box<dyn Send>)>>,
+    grace_period: Duration,
+}
+
+impl Collector {
+    /// Constructs a new collector, without grace period.
+    pub fn new() -> Self {
+        Self::with_grace_period(Duration::ZERO)
+    }
+
+    /// Constructs a new collector, whose retired values are dropped only after the given
+    /// grace period.
+    pub fn with_grace_period(grace_period: Duration) -> Self {
+        Self {
+            queue: Mutex::new(VecDeque::new()),
+            grace_period,
+        }
+    }
+
+    fn queue(&self) -> MutexGuard<'_, VecDeque<(Instant, Box<dyn Send>)>> {
+        self.queue.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Returns the grace period of the collector.
+    pub fn grace_period(&self) -> Duration {
+        self.grace_period
+    }
+
+    /// Retires a value, which will be dropped by a subsequent [`collect`](Self::collect).
+    pub fn retire<T: Send + 'static>(&self, value: T) {
+        let retired = (Instant::now(), Box::new(value) as Box<dyn Send>);
+        self.queue().push_back(retired);
+    }
+
+    /// Returns the number of retired values not dropped yet.
+    pub fn pending(&self) -> usize {
+        self.queue().len()
+    }
+
+    /// Drops the retired values whose grace period has elapsed, and returns their number.
+    ///
+    /// Values are dropped outside the queue lock, so retiring is never blocked by a drop.
+    pub fn collect(&self) -> usize {
+        let now = Instant::now();
+        let mut queue = self.queue();
+        let expired = queue
+            .iter()
+            .take_while(|(retired_at, _)| now.duration_since(*retired_at) >= self.grace_period)
+            .count();
+        let collected = queue.drain(..expired).collect::<Vec<_>>();
+        drop(queue);
+        collected.len()
+    }
+
+    /// Spawns a background thread collecting retired values at the given interval.
+    ///
+    /// The thread stops when the collector is dropped.
+    pub fn spawn_background(self: &Arc<Self>, interval: Duration) -> thread::JoinHandle<()> {
+        let collector = Arc::downgrade(self);
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            match Weak::upgrade(&collector) {
+                Some(collector) => drop(collector.collect()),
+                None => return,
+            }
+        })
+    }
+}
+
+impl Default for Collector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Collector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Collector")
+            .field("pending", &self.pending())
+            .field("grace_period", &self.grace_period)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::sync::Arc;
+    use core::{
+        sync::atomic::{AtomicUsize, Ordering::Relaxed},
+        time::Duration,
+    };
+
+    use crate::{collector::Collector, domain, AtomicArc};
+
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+    struct Dropped;
+    impl Drop for Dropped {
+        fn drop(&mut self) {
+            DROP_COUNT.fetch_add(1, Relaxed);
+        }
+    }
+
+    #[test]
+    fn deferred_drop() {
+        domain!(TestDomain(1));
+        let collector = Collector::new();
+        let atomic_arc = AtomicArc::<Dropped, TestDomain>::from(Dropped);
+        atomic_arc.store_deferred(Arc::new(Dropped), &collector);
+        let with_grace = Collector::with_grace_period(Duration::from_secs(3600));
+        atomic_arc.store_deferred(Arc::new(Dropped), &with_grace);
+        assert_eq!(DROP_COUNT.load(Relaxed), 0);
+        assert_eq!(with_grace.collect(), 0);
+        assert_eq!(collector.collect(), 1);
+        assert_eq!(DROP_COUNT.load(Relaxed), 1);
+        let collector = Arc::new(collector);
+        let handle = collector.spawn_background(Duration::from_millis(1));
+        collector.retire(());
+        drop(collector);
+        handle.join().unwrap();
+    }
+}
@@ -623,12 +623,7 @@ macro_rules! pthread_domain {
     };
 }
 
-#[cfg(any(
-    feature = "domain-gc",
-    not(target_pointer_width = "64"),
-    hazarc_force_active_writer_count_64bit
-))]
-fn abort_before_overflow(n: usize, msg: &str) {
+pub(crate) fn abort_before_overflow(n: usize, msg: &str) {
     if n >= usize::MAX / 2 {
         struct PanicInDrop;
         impl Drop for PanicInDrop {
@@ -0,0 +1,246 @@
+//! Versioned atomic Arc storage retaining its last stored values.
+//!
+//! The current Arc and the retained ones are stored together in a single [`History`] Arc, so
+//! a write updates both atomically, with a single pass over the domain nodes. The ring of
+//! retained Arcs is then copied by every write.
+
+use alloc::{sync::Arc, vec::Vec};
+use core::{array, fmt, ops::Deref};
+
+use crate::{
+    domain::Domain, versioned::Versioned, write_policy::WritePolicy, ArcBorrow, AtomicArc,
+};
+
+/// The last `N` Arcs stored in a [`HistoryAtomicArc`], including the current one, with their
+/// version.
+///
+/// It dereferences to the current entry.
+pub struct History<T, const N: usize> {
+    version: u64,
+    // indexed by version, the current one is always present
+    ring: [Option<Versioned<Arc<T>>>; N],
+}
+
+impl<T, const N: usize> History<T, N> {
+    #[inline(always)]
+    fn slot(&self, version: u64) -> &Option<Versioned<Arc<T>>> {
+        &self.ring[(version % N as u64) as usize]
+    }
+
+    /// Returns the current Arc, with its version.
+    #[inline]
+    pub fn current(&self) -> &Versioned<Arc<T>> {
+        self.slot(self.version).as_ref().unwrap()
+    }
+
+    /// Returns the Arc stored `k` versions before the current one, if it is retained.
+    ///
+    /// `previous(0)` returns the current Arc.
+    pub fn previous(&self, k: usize) -> Option<&Versioned<Arc<T>>> {
+        if k >= N {
+            return None;
+        }
+        // versions are contiguous, so the slot holds the requested version
+        self.slot(self.version.checked_sub(k as u64)?).as_ref()
+    }
+
+    /// Returns an iterator over the retained Arcs, from the most recent to the oldest.
+    pub fn iter(&self) -> impl Iterator<Item = &Versioned<Arc<T>>> {
+        (0..N).map_while(|k| self.previous(k))
+    }
+
+    /// Returns the history with the Arc stored with the next version.
+    fn next(&self, arc: Arc<T>) -> Self {
+        let mut entry = Versioned::new(arc);
+        entry.set_next_version(self.current());
+        let version = entry.version();
+        let mut ring = self.ring.clone();
+        ring[(version % N as u64) as usize] = Some(entry);
+        Self { version, ring }
+    }
+}
+
+impl<T, const N: usize> Deref for History<T, N> {
+    type Target = Versioned<Arc<T>>;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.current()
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for History<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// A versioned [`AtomicArc`] retaining its last `N` stored Arcs, including the current one.
+///
+/// Loads return the whole [`History`], dereferencing to the current value, so the retained Arcs
+/// are always consistent with it.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hazarc::history::HistoryAtomicArc;
+/// # hazarc::domain!(Domain(8));
+/// let atomic_arc = HistoryAtomicArc::<_, 3, Domain>::new(0);
+/// atomic_arc.store(1);
+/// atomic_arc.store(2);
+/// assert_eq!(**atomic_arc.load_previous(2).unwrap(), 0);
+/// atomic_arc.rollback(1);
+/// let history = atomic_arc.load();
+/// assert_eq!((history.version(), ***history.current()), (3, 1));
+/// assert!(history.previous(3).is_none());
+/// ```
+#[cfg(feature = "default-domain")]
+pub struct HistoryAtomicArc<
+    T,
+    const N: usize,
+    D: Domain = crate::DefaultDomain,
+    W: WritePolicy = crate::write_policy::Concurrent,
+>(AtomicArc<History<T, N>, D, W>);
+/// A versioned [`AtomicArc`] retaining its last `N` stored Arcs, including the current one.
+///
+/// Loads return the whole [`History`], dereferencing to the current value, so the retained Arcs
+/// are always consistent with it.
+#[cfg(not(feature = "default-domain"))]
+pub struct HistoryAtomicArc<
+    T,
+    const N: usize,
+    D: Domain,
+    W: WritePolicy = crate::write_policy::Concurrent,
+>(AtomicArc<History<T, N>, D, W>);
+
+impl<T, const N: usize, D: Domain, W: WritePolicy> HistoryAtomicArc<T, N, D, W> {
+    const NON_EMPTY: () = assert!(N > 0, "HistoryAtomicArc requires N > 0");
+
+    /// Constructs a new Arc atomic storage, with version 0.
+    pub fn new(value: T) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let _ = Self::NON_EMPTY;
+        let mut ring = array::from_fn(|_| None);
+        ring[0] = Some(Versioned::new(Arc::new(value)));
+        Self(AtomicArc::from(History { version: 0, ring }))
+    }
+
+    /// Loads the borrowed history, dereferencing to the current value.
+    #[inline]
+    pub fn load(&self) -> ArcBorrow<History<T, N>> {
+        self.0.load()
+    }
+
+    /// Loads an owned Arc, with its version.
+    #[inline]
+    pub fn load_owned(&self) -> Versioned<Arc<T>> {
+        self.load().current().clone()
+    }
+
+    /// Returns the current version.
+    #[inline]
+    pub fn version(&self) -> u64 {
+        self.load().version()
+    }
+
+    /// Loads the Arc stored `k` versions before the current one, if it is retained.
+    ///
+    /// `load_previous(0)` returns the current Arc.
+    pub fn load_previous(&self, k: usize) -> Option<Versioned<Arc<T>>> {
+        self.load().previous(k).cloned()
+    }
+
+    /// Returns the retained Arcs, from the most recent to the oldest.
+    pub fn history(&self) -> Vec<Versioned<Arc<T>>> {
+        self.load().iter().cloned().collect()
+    }
+
+    /// Stores the Arc returned by `f` with the next version, and returns the previous Arc.
+    ///
+    /// Returns `None` if `f` does.
+    fn swap_impl(
+        &self,
+        mut f: impl FnMut(&History<T, N>) -> Option<Arc<T>>,
+    ) -> Option<Versioned<Arc<T>>> {
+        let mut current = self.load();
+        loop {
+            let new = Arc::new(current.next(f(&current)?));
+            match self.0.compare_exchange(&*current, new) {
+                Ok(old_history) => return Some(old_history.current().clone()),
+                Err(history) => current = history,
+            }
+        }
+    }
+
+    /// Stores the value with the next version, and returns the previous Arc.
+    pub fn swap(&self, value: T) -> Versioned<Arc<T>> {
+        let arc = Arc::new(value);
+        self.swap_impl(|_| Some(arc.clone())).unwrap()
+    }
+
+    /// Stores the value with the next version.
+    pub fn store(&self, value: T) {
+        drop(self.swap(value));
+    }
+
+    /// Stores again the Arc stored `k` versions before the current one, with the next version,
+    /// and returns the previous Arc.
+    ///
+    /// The retained Arc is stored with a compare-and-swap, so it is restored relative to the
+    /// actual current version even with concurrent writes. Returns `None` if the Arc is not
+    /// retained.
+    pub fn rollback(&self, k: usize) -> Option<Versioned<Arc<T>>> {
+        self.swap_impl(|history| Some((**history.previous(k)?).clone()))
+    }
+}
+
+impl<T: fmt::Debug, const N: usize, D: Domain, W: WritePolicy> fmt::Debug
+    for HistoryAtomicArc<T, N, D, W>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("HistoryAtomicArc")
+            .field(&**self.load())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{sync::Arc, vec::Vec};
+
+    use crate::{domain, history::HistoryAtomicArc, write_policy::Concurrent};
+
+    #[test]
+    fn history() {
+        domain!(TestDomain(1));
+        let atomic_arc = HistoryAtomicArc::<usize, 2, TestDomain, Concurrent>::new(0);
+        let values = || -> Vec<_> {
+            let history = atomic_arc.history();
+            history.iter().map(|arc| (arc.version(), ***arc)).collect()
+        };
+        assert_eq!(values(), [(0, 0)]);
+        assert_eq!(atomic_arc.swap(1).version(), 0);
+        assert_eq!(values(), [(1, 1), (0, 0)]);
+        let history = atomic_arc.load();
+        atomic_arc.store(2);
+        assert_eq!(values(), [(2, 2), (1, 1)]);
+        // a loaded history is consistent with its current value
+        assert_eq!((history.version(), ***history.previous(1).unwrap()), (1, 0));
+        assert!(atomic_arc.load_previous(2).is_none());
+        assert!(atomic_arc.rollback(2).is_none());
+        let one = atomic_arc.load_previous(1).unwrap();
+        assert_eq!(atomic_arc.rollback(1).unwrap().version(), 2);
+        assert_eq!(values(), [(3, 1), (2, 2)]);
+        // the retained Arc is stored again, without cloning the value
+        assert!(Arc::ptr_eq(&atomic_arc.load_owned(), &one));
+    }
+
+    #[test]
+    fn rollback_non_clone() {
+        domain!(TestDomain(1));
+        struct NonClone(usize);
+        let atomic_arc = HistoryAtomicArc::<NonClone, 2, TestDomain, Concurrent>::new(NonClone(0));
+        atomic_arc.store(NonClone(1));
+        atomic_arc.rollback(1).unwrap();
+        assert_eq!(atomic_arc.load().0, 0);
+    }
+}
@@ -0,0 +1,229 @@
+//! Lazily-initialized atomic storage for `Arc`-like pointers.
+
+use core::{
+    fmt,
+    ops::Deref,
+    sync::atomic::Ordering::{Relaxed, SeqCst},
+};
+
+use crate::{
+    arc::{ArcPtr, NonNullArcPtr},
+    atomic::{ArcPtrBorrow, AtomicOptionArcPtr, CachedOrReloaded},
+    cache::AtomicArcRef,
+    domain::Domain,
+    write_policy::WritePolicy,
+};
+
+/// An atomic storage for generic `Arc`-like pointers, initialized on first access.
+///
+/// The initialization function returns the pointed value, which is then wrapped into the Arc.
+/// Contrary to [`AtomicArcPtr`](crate::atomic::AtomicArcPtr), it can be constructed in a `const`
+/// context, so it can be declared in a `static`. Once initialized, it behaves exactly like an
+/// `AtomicArcPtr`; the initialization check is folded into the pointer load, so it adds no
+/// overhead to the hot path.
+///
+/// # Examples
+///
+/// ```rust
+/// # hazarc::domain!(Domain(8));
+/// # type LazyAtomicArc<T> = hazarc::LazyAtomicArc<T, Domain>;
+/// static CONFIG: LazyAtomicArc<usize> = LazyAtomicArc::new(|| 42);
+/// assert_eq!(**CONFIG.load(), 42);
+/// CONFIG.store(0.into());
+/// assert_eq!(**CONFIG.load(), 0);
+/// ```
+pub struct LazyAtomicArcPtr<A: NonNullArcPtr, D: Domain, W: WritePolicy, F> {
+    inner: AtomicOptionArcPtr<A, D, W>,
+    init: F,
+}
+
+impl<A: NonNullArcPtr, D: Domain, W: WritePolicy, F> LazyAtomicArcPtr<A, D, W, F> {
+    /// Constructs a new lazy Arc atomic storage, with the given initialization function.
+    #[inline]
+    pub const fn new(init: F) -> Self {
+        Self {
+            inner: AtomicOptionArcPtr::none(),
+            init,
+        }
+    }
+
+    /// Returns `true` if the storage has been initialized.
+    #[inline]
+    pub fn is_initialized(&self) -> bool {
+        !self.inner.is_none()
+    }
+}
+
+impl<A, D, W, F> LazyAtomicArcPtr<A, D, W, F>
+where
+    A: NonNullArcPtr + Deref + From<A::Target>,
+    A::Target: Sized,
+    D: Domain,
+    W: WritePolicy,
+    F: Fn() -> A::Target,
+{
+    /// Loads a borrowed Arc, initializing the storage if needed.
+    ///
+    /// Concurrent initializations race to store their Arc, like
+    /// [`AtomicOptionArc::get_or_init`](AtomicOptionArcPtr::get_or_init).
+    #[inline]
+    pub fn load(&self) -> ArcPtrBorrow<A> {
+        let ptr = self.inner.inner().load_ptr(Relaxed);
+        if ptr.is_null() {
+            return self.load_init();
+        }
+        // SAFETY: the storage is never reset to `None` once initialized
+        unsafe {
+            self.inner
+                .inner()
+                .load_impl(ptr)
+                .transpose()
+                .unwrap_unchecked()
+        }
+    }
+
+    #[cold]
+    #[inline(never)]
+    fn load_init(&self) -> ArcPtrBorrow<A> {
+        self.inner.get_or_init(&self.init)
+    }
+
+    /// Loads an owned Arc, initializing the storage if needed.
+    #[inline]
+    pub fn load_owned(&self) -> A {
+        self.load().into_owned()
+    }
+
+    /// Returns a reference to the cached Arc if it is up-to-date, or loads the latest Arc.
+    #[inline]
+    pub fn load_cached_or_reload<'a>(&self, cached: &'a A) -> CachedOrReloaded<'a, A> {
+        if self.inner.inner().load_ptr(SeqCst) == ArcPtr::as_ptr(cached) {
+            CachedOrReloaded::Cached(cached)
+        } else {
+            CachedOrReloaded::Reloaded(self.load_cold())
+        }
+    }
+
+    #[cold]
+    #[inline(never)]
+    fn load_cold(&self) -> ArcPtrBorrow<A> {
+        self.load()
+    }
+
+    /// Returns a reference to the cached Arc, updating it when it is outdated.
+    ///
+    /// See [`Cache`](crate::Cache) for a convenient wrapper around this method.
+    #[inline]
+    pub fn load_cached<'a>(&self, cached: &'a mut A) -> &'a A {
+        if self.inner.inner().load_ptr(SeqCst) != ArcPtr::as_ptr(cached) {
+            *cached = self.load_cold().into_owned();
+        }
+        cached
+    }
+
+    /// Stores the Arc and returns the previous one.
+    ///
+    /// Returns `None` if the storage was not initialized; the initialization function is not
+    /// called.
+    pub fn swap(&self, arc: A) -> Option<A> {
+        self.inner.swap(Some(arc))
+    }
+
+    /// Stores the Arc, dropping the previous one.
+    ///
+    /// The initialization function is not called if the storage was not initialized.
+    pub fn store(&self, arc: A) {
+        self.inner.store(Some(arc));
+    }
+
+    /// Consumes the atomic storage and returns the stored Arc, initializing it if needed.
+    #[inline]
+    pub fn into_owned(self) -> A {
+        let Self { inner, init } = self;
+        inner.into_owned().unwrap_or_else(|| A::from(init()))
+    }
+}
+
+impl<A: NonNullArcPtr + fmt::Debug, D: Domain, W: WritePolicy, F> fmt::Debug
+    for LazyAtomicArcPtr<A, D, W, F>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("LazyAtomicArcPtr")
+            .field(&self.inner.load().as_deref())
+            .finish()
+    }
+}
+
+impl<A, D, W, F> AtomicArcRef for LazyAtomicArcPtr<A, D, W, F>
+where
+    A: NonNullArcPtr + Deref + From<A::Target>,
+    A::Target: Sized,
+    D: Domain,
+    W: WritePolicy,
+    F: Fn() -> A::Target,
+{
+    type Arc = A;
+    type Owned = A;
+    type LoadCached<'a>
+        = &'a Self::Arc
+    where
+        Self::Arc: 'a;
+    type LoadCachedOrReload<'a>
+        = CachedOrReloaded<'a, Self::Arc>
+    where
+        Self::Arc: 'a;
+    #[inline]
+    fn load_owned(&self) -> Self::Owned {
+        self.load_owned()
+    }
+    #[inline(always)]
+    fn load_cached<'a>(&self, cached: &'a mut Self::Owned) -> Self::LoadCached<'a> {
+        self.load_cached(cached)
+    }
+    #[inline(always)]
+    fn load_cached_or_reload<'a>(&self, cached: &'a Self::Owned) -> Self::LoadCachedOrReload<'a> {
+        self.load_cached_or_reload(cached)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::sync::Arc;
+    use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+    use crate::{domain, Cache, LazyAtomicArc};
+
+    #[test]
+    fn lazy_init() {
+        domain!(TestDomain(1));
+        static INIT_COUNT: AtomicUsize = AtomicUsize::new(0);
+        static LAZY: LazyAtomicArc<usize, TestDomain> = LazyAtomicArc::new(|| {
+            INIT_COUNT.fetch_add(1, Relaxed);
+            0
+        });
+        assert!(!LAZY.is_initialized());
+        assert_eq!(**LAZY.load(), 0);
+        assert_eq!(**LAZY.load(), 0);
+        assert_eq!(INIT_COUNT.load(Relaxed), 1);
+        let mut cache = Cache::new(&LAZY);
+        assert_eq!(**cache.load(), 0);
+        assert_eq!(LAZY.swap(Arc::new(1)).as_deref(), Some(&0));
+        assert_eq!(**cache.load(), 1);
+        assert_eq!(INIT_COUNT.load(Relaxed), 1);
+    }
+
+    #[test]
+    fn swap_uninit() {
+        domain!(TestDomain(1));
+        static INIT_COUNT: AtomicUsize = AtomicUsize::new(0);
+        let lazy = LazyAtomicArc::<usize, TestDomain>::new(|| {
+            INIT_COUNT.fetch_add(1, Relaxed);
+            0
+        });
+        assert_eq!(lazy.swap(Arc::new(1)), None);
+        assert_eq!(INIT_COUNT.load(Relaxed), 0);
+        assert_eq!(*lazy.into_owned(), 1);
+        let lazy = LazyAtomicArc::<usize, TestDomain>::new(|| 0);
+        assert_eq!(*lazy.into_owned(), 0);
+    }
+}
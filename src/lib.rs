@@ -80,9 +80,12 @@
 //! - `default-domain` *(enabled by default)*: provides a default domain parameter to [`AtomicArc`]
 //!   using standard thread-local storage.
 //! - `domain-gc`: enables automatic domain deallocation.
+//! - `futures-core`: implements `futures_core::Stream` for [`notify::Changes`].
 //! - `pthread-domain`: enables the `pthread_domain!` macro for `no_std` environments using
 //!   POSIX pthread thread-local storage.
 //! - `serde`: enables `serde` support.
+//! - `std` *(enabled by `default-domain`)*: enables blocking APIs like
+//!   [`AtomicArc::wait_changed`].
 //!
 //! # Write policy
 //!
@@ -125,6 +128,16 @@
 //! If there are concurrent writes on the same `AtomicArc`, they may execute `AtomicArc::load`
 //! internally, with the same consequences on wait-freedom.
 //!
+//! #### Change notification
+//!
+//! Writes wake the tasks and threads waiting for a change, e.g. with
+//! [`AtomicArc::changed`](atomic::AtomicArcPtr::changed) or `AtomicArc::wait_changed`.
+//! Waiters are registered in [buckets](notify) shared by unrelated storages, each protected by
+//! a spinlock, which writes acquire whenever the bucket of the storage has registered waiters.
+//! Writes are then neither wait-free nor lock-free, as they may wait for a thread holding the
+//! lock. Without any waiter in the bucket, writes only check its waiter count, and keep their
+//! wait-freedom guarantees. Loads are never impacted.
+//!
 //! [access the thread-local node]: domain::Domain::get_or_acquire_thread_local_node
 //!
 //! # Safety
@@ -136,18 +149,38 @@
 #![warn(missing_docs)]
 #![no_std]
 extern crate alloc;
-#[cfg(any(feature = "default-domain", test))]
+#[cfg(any(feature = "std", test))]
 extern crate std;
 
 use alloc::sync::Arc;
 
 pub mod arc;
 pub mod atomic;
+pub mod batch;
 pub mod cache;
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub mod collector;
 pub mod domain;
+pub mod history;
+pub mod lazy;
 mod msrv;
+pub mod notify;
+pub mod pair;
+pub mod rcu;
+pub mod retire;
 #[cfg(feature = "serde")]
 mod serde;
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub mod sharded;
+pub mod snapshot;
+pub mod stm;
+pub mod tagged;
+pub mod vec;
+pub mod versioned;
+pub mod watch;
+pub mod weak;
 pub mod write_policy;
 
 #[cfg(feature = "default-domain")]
@@ -177,9 +210,54 @@ pub type AtomicOptionArc<T, D = DefaultDomain, W = write_policy::Concurrent> =
 #[cfg(not(feature = "default-domain"))]
 pub type AtomicOptionArc<T, D, W = write_policy::Concurrent> =
     atomic::AtomicOptionArcPtr<Arc<T>, D, W>;
+/// Alias for `AtomicArcPtr<ThinArc<T>>`
+#[cfg(feature = "default-domain")]
+#[cfg_attr(docsrs, doc(cfg(all())))]
+pub type AtomicThinArc<T, D = DefaultDomain, W = write_policy::Concurrent> =
+    atomic::AtomicArcPtr<arc::ThinArc<T>, D, W>;
+/// Alias for `AtomicArcPtr<ThinArc<T>>`
+#[cfg(not(feature = "default-domain"))]
+pub type AtomicThinArc<T, D, W = write_policy::Concurrent> =
+    atomic::AtomicArcPtr<arc::ThinArc<T>, D, W>;
+/// Alias for `AtomicArcPtr<MaybeStatic<T>>`
+#[cfg(feature = "default-domain")]
+#[cfg_attr(docsrs, doc(cfg(all())))]
+pub type AtomicMaybeStatic<T, D = DefaultDomain, W = write_policy::Concurrent> =
+    atomic::AtomicArcPtr<arc::MaybeStatic<T>, D, W>;
+/// Alias for `AtomicArcPtr<MaybeStatic<T>>`
+#[cfg(not(feature = "default-domain"))]
+pub type AtomicMaybeStatic<T, D, W = write_policy::Concurrent> =
+    atomic::AtomicArcPtr<arc::MaybeStatic<T>, D, W>;
+/// Alias for `TaggedAtomicArcPtr<Arc<T>, D, W, BITS>`
+#[cfg(feature = "default-domain")]
+#[cfg_attr(docsrs, doc(cfg(all())))]
+pub type TaggedAtomicArc<T, const BITS: u32, D = DefaultDomain, W = write_policy::Concurrent> =
+    tagged::TaggedAtomicArcPtr<Arc<T>, D, W, BITS>;
+/// Alias for `TaggedAtomicArcPtr<Arc<T>, D, W, BITS>`
+#[cfg(not(feature = "default-domain"))]
+pub type TaggedAtomicArc<T, const BITS: u32, D, W = write_policy::Concurrent> =
+    tagged::TaggedAtomicArcPtr<Arc<T>, D, W, BITS>;
+/// Alias for `weak::AtomicWeak<T, D, W>` with default parameters
+#[cfg(feature = "default-domain")]
+#[cfg_attr(docsrs, doc(cfg(all())))]
+pub type AtomicWeak<T, D = DefaultDomain, W = write_policy::Concurrent> = weak::AtomicWeak<T, D, W>;
+/// Alias for `weak::AtomicWeak<T, D, W>` with default parameters
+#[cfg(not(feature = "default-domain"))]
+pub type AtomicWeak<T, D, W = write_policy::Concurrent> = weak::AtomicWeak<T, D, W>;
+/// Alias for `LazyAtomicArcPtr<Arc<T>, D, W, F>`
+#[cfg(feature = "default-domain")]
+#[cfg_attr(docsrs, doc(cfg(all())))]
+pub type LazyAtomicArc<T, D = DefaultDomain, W = write_policy::Concurrent, F = fn() -> T> =
+    lazy::LazyAtomicArcPtr<Arc<T>, D, W, F>;
+/// Alias for `LazyAtomicArcPtr<Arc<T>, D, W, F>`
+#[cfg(not(feature = "default-domain"))]
+pub type LazyAtomicArc<T, D, W = write_policy::Concurrent, F = fn() -> T> =
+    lazy::LazyAtomicArcPtr<Arc<T>, D, W, F>;
 /// Alias for `ArcPtrBorrow<Arc<T>>`
 pub type ArcBorrow<T> = atomic::ArcPtrBorrow<Arc<T>>;
 
+pub use batch::batch;
 pub use cache::Cache;
+pub use snapshot::snapshot;
 
 const NULL: *mut () = core::ptr::null_mut();
@@ -0,0 +1,386 @@
+//! Change notification of atomic Arc storage.
+//!
+//! Waiters register a [`Waker`] in a global registry keyed by the storage address, and writers
+//! wake them after storing a new Arc. The registry is split into buckets selected by hashing the
+//! address, each one with its own lock and waiter count. A bucket is only locked by writers when
+//! it has registered wakers, so writes don't pay for notification otherwise, and reads are never
+//! impacted.
+
+use alloc::vec::Vec;
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    future::Future,
+    ops::{Deref, DerefMut},
+    pin::Pin,
+    sync::atomic::{
+        AtomicBool, AtomicUsize,
+        Ordering::{Acquire, Relaxed, Release, SeqCst},
+    },
+    task::{Context, Poll, Waker},
+};
+
+use crossbeam_utils::{Backoff, CachePadded};
+
+#[allow(unused_imports)]
+use crate::msrv::StrictProvenance;
+use crate::{
+    arc::ArcPtr,
+    atomic::{ArcPtrBorrow, AtomicArcPtr},
+    domain::Domain,
+    write_policy::WritePolicy,
+};
+
+struct Waiter {
+    addr: usize,
+    key: usize,
+    waker: Waker,
+}
+
+struct Bucket {
+    locked: AtomicBool,
+    // Number of registered wakers, checked by writers before locking the bucket
+    waiter_count: AtomicUsize,
+    waiters: UnsafeCell<Vec<Waiter>>,
+}
+
+// SAFETY: waiters are only accessed with the lock acquired
+unsafe impl Sync for Bucket {}
+
+impl Bucket {
+    fn get(addr: usize) -> &'static Self {
+        // Fibonacci hashing, as storages are often contiguous
+        const MULTIPLIER: usize = 0x9E37_79B9_7F4A_7C15_u64 as usize;
+        &BUCKETS[addr.wrapping_mul(MULTIPLIER) >> (usize::BITS - BUCKET_BITS)]
+    }
+
+    fn lock(&self) -> BucketGuard<'_> {
+        let backoff = Backoff::new();
+        while (self.locked)
+            .compare_exchange_weak(false, true, Acquire, Relaxed)
+            .is_err()
+        {
+            backoff.snooze();
+        }
+        BucketGuard(self)
+    }
+}
+
+struct BucketGuard<'a>(&'a Bucket);
+
+impl Deref for BucketGuard<'_> {
+    type Target = Vec<Waiter>;
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: the lock is acquired
+        unsafe { &*self.0.waiters.get() }
+    }
+}
+
+impl DerefMut for BucketGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: the lock is acquired
+        unsafe { &mut *self.0.waiters.get() }
+    }
+}
+
+impl Drop for BucketGuard<'_> {
+    fn drop(&mut self) {
+        self.0.locked.store(false, Release);
+    }
+}
+
+const BUCKET_BITS: u32 = 6;
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_BUCKET: CachePadded<Bucket> = CachePadded::new(Bucket {
+    locked: AtomicBool::new(false),
+    waiter_count: AtomicUsize::new(0),
+    waiters: UnsafeCell::new(Vec::new()),
+});
+static BUCKETS: [CachePadded<Bucket>; 1 << BUCKET_BITS] = [EMPTY_BUCKET; 1 << BUCKET_BITS];
+static NEXT_KEY: AtomicUsize = AtomicUsize::new(0);
+
+/// Registration of a waker in the global registry, unregistered on drop.
+#[derive(Debug, Default)]
+pub(crate) struct WaiterKey(Option<(usize, usize)>);
+
+impl WaiterKey {
+    /// Returns `Ready` if `f` returns `Some`, otherwise registers the waker for the given
+    /// address, and calls `f` again.
+    ///
+    /// `f` must be called again after registration, as a notification may happen before it.
+    pub(crate) fn poll_until<R>(
+        &mut self,
+        addr: usize,
+        waker: &Waker,
+        mut f: impl FnMut() -> Option<R>,
+    ) -> Poll<R> {
+        if let Some(res) = f() {
+            self.unregister();
+            return Poll::Ready(res);
+        }
+        self.register(addr, waker);
+        match f() {
+            Some(res) => {
+                self.unregister();
+                Poll::Ready(res)
+            }
+            None => Poll::Pending,
+        }
+    }
+
+    fn register(&mut self, addr: usize, waker: &Waker) {
+        if self.0.map_or(false, |(a, _)| a != addr) {
+            self.unregister();
+        }
+        let bucket = Bucket::get(addr);
+        let mut waiters = bucket.lock();
+        if let Some(waiter) = (self.0).and_then(|(_, k)| waiters.iter_mut().find(|w| w.key == k)) {
+            if !waiter.waker.will_wake(waker) {
+                waiter.waker = waker.clone();
+            }
+            return;
+        }
+        let key = NEXT_KEY.fetch_add(1, Relaxed);
+        self.0 = Some((addr, key));
+        waiters.push(Waiter {
+            addr,
+            key,
+            waker: waker.clone(),
+        });
+        // SeqCst increment before checking the condition again, matching the SeqCst store of
+        // the writer before it checks the waiter count, so either the waiter sees the change,
+        // or the writer sees the waiter
+        bucket.waiter_count.fetch_add(1, SeqCst);
+    }
+
+    fn unregister(&mut self) {
+        let Some((addr, key)) = self.0.take() else {
+            return;
+        };
+        let bucket = Bucket::get(addr);
+        let mut waiters = bucket.lock();
+        if let Some(idx) = waiters.iter().position(|w| w.key == key) {
+            waiters.swap_remove(idx);
+            bucket.waiter_count.fetch_sub(1, Relaxed);
+        }
+    }
+}
+
+impl Drop for WaiterKey {
+    fn drop(&mut self) {
+        self.unregister();
+    }
+}
+
+/// Wakes all the wakers registered for the given address.
+#[inline]
+pub(crate) fn notify(addr: usize) {
+    let bucket = Bucket::get(addr);
+    if bucket.waiter_count.load(SeqCst) != 0 {
+        notify_slow(bucket, addr);
+    }
+}
+
+#[cold]
+#[inline(never)]
+fn notify_slow(bucket: &Bucket, addr: usize) {
+    let mut woken = Vec::new();
+    let mut waiters = bucket.lock();
+    let mut idx = 0;
+    while idx < waiters.len() {
+        if waiters[idx].addr == addr {
+            woken.push(waiters.swap_remove(idx).waker);
+        } else {
+            idx += 1;
+        }
+    }
+    bucket.waiter_count.fetch_sub(woken.len(), Relaxed);
+    drop(waiters);
+    woken.into_iter().for_each(Waker::wake);
+}
+
+/// Future returned by [`AtomicArcPtr::changed`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Changed<'a, A: ArcPtr, D: Domain, W: WritePolicy> {
+    atomic_arc: &'a AtomicArcPtr<A, D, W>,
+    // kept alive, so its address cannot be reused by a new Arc while waiting
+    seen: A,
+    key: WaiterKey,
+}
+
+impl<'a, A: ArcPtr, D: Domain, W: WritePolicy> Changed<'a, A, D, W> {
+    pub(crate) fn new(atomic_arc: &'a AtomicArcPtr<A, D, W>, seen: A) -> Self {
+        Self {
+            atomic_arc,
+            seen,
+            key: WaiterKey::default(),
+        }
+    }
+}
+
+impl<A: ArcPtr, D: Domain, W: WritePolicy> Future for Changed<'_, A, D, W> {
+    type Output = ArcPtrBorrow<A>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let (atomic_arc, seen) = (this.atomic_arc, A::as_ptr(&this.seen));
+        this.key.poll_until(atomic_arc.addr(), cx.waker(), || {
+            let ptr = atomic_arc.load_ptr(SeqCst);
+            (ptr != seen).then(|| atomic_arc.load_impl(ptr))
+        })
+    }
+}
+
+// Fields are never pinned
+impl<A: ArcPtr, D: Domain, W: WritePolicy> Unpin for Changed<'_, A, D, W> {}
+
+impl<A: ArcPtr, D: Domain, W: WritePolicy> fmt::Debug for Changed<'_, A, D, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Changed").finish_non_exhaustive()
+    }
+}
+
+/// Stream of the Arcs stored in an atomic storage, returned by [`AtomicArcPtr::changes`].
+///
+/// It implements `futures_core::Stream` with the `futures-core` feature.
+pub struct Changes<'a, A: ArcPtr, D: Domain, W: WritePolicy> {
+    atomic_arc: &'a AtomicArcPtr<A, D, W>,
+    last: A,
+    key: WaiterKey,
+}
+
+impl<'a, A: ArcPtr, D: Domain, W: WritePolicy> Changes<'a, A, D, W> {
+    pub(crate) fn new(atomic_arc: &'a AtomicArcPtr<A, D, W>) -> Self {
+        Self {
+            atomic_arc,
+            last: atomic_arc.load_owned(),
+            key: WaiterKey::default(),
+        }
+    }
+
+    /// Returns the last yielded Arc, or the Arc loaded at the stream creation.
+    pub fn last(&self) -> &A {
+        &self.last
+    }
+
+    /// Polls the next stored Arc, registering the current task to be woken when a new Arc is
+    /// stored.
+    ///
+    /// Intermediate Arcs may be skipped if several ones are stored between two polls.
+    pub fn poll_change(&mut self, cx: &mut Context<'_>) -> Poll<A> {
+        let (atomic_arc, last) = (self.atomic_arc, &mut self.last);
+        self.key.poll_until(atomic_arc.addr(), cx.waker(), || {
+            let ptr = atomic_arc.load_ptr(SeqCst);
+            if ptr == A::as_ptr(last) {
+                return None;
+            }
+            *last = atomic_arc.load_impl(ptr).into_owned();
+            Some(last.clone())
+        })
+    }
+}
+
+// Fields are never pinned
+impl<A: ArcPtr, D: Domain, W: WritePolicy> Unpin for Changes<'_, A, D, W> {}
+
+impl<A: ArcPtr + fmt::Debug, D: Domain, W: WritePolicy> fmt::Debug for Changes<'_, A, D, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Changes")
+            .field("last", &self.last)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "futures-core")]
+impl<A: ArcPtr, D: Domain, W: WritePolicy> futures_core::Stream for Changes<'_, A, D, W> {
+    type Item = A;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_change(cx).map(Some)
+    }
+}
+
+/// Polls the future to completion, parking the current thread while it is pending.
+#[cfg(feature = "std")]
+pub(crate) fn block_on<F: Future + Unpin>(mut future: F) -> F::Output {
+    use alloc::{sync::Arc, task::Wake};
+    use std::thread::{self, Thread};
+
+    struct ThreadWaker(Thread);
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = Pin::new(&mut future).poll(&mut cx) {
+            return output;
+        }
+        thread::park();
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use alloc::{sync::Arc, task::Wake};
+    use core::{
+        future::Future,
+        pin::Pin,
+        sync::atomic::{AtomicUsize, Ordering::Relaxed},
+        task::{Context, Poll, Waker},
+    };
+
+    use crate::{domain, AtomicArc};
+
+    /// Waker counting its wake-ups, shared with the tests of the other notified modules.
+    #[derive(Default)]
+    pub(crate) struct CountWaker(pub(crate) AtomicUsize);
+    impl Wake for CountWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Relaxed);
+        }
+    }
+
+    #[test]
+    fn changed() {
+        domain!(TestDomain(1));
+        let atomic_arc = AtomicArc::<usize, TestDomain>::from(0);
+        let count = Arc::new(CountWaker::default());
+        let waker = Waker::from(count.clone());
+        let mut cx = Context::from_waker(&waker);
+        let seen = atomic_arc.load_owned();
+        let mut changed = atomic_arc.changed(&seen);
+        // the future retains `seen`, so its address cannot be reused
+        assert_eq!(Arc::strong_count(&seen), 3);
+        assert!(Pin::new(&mut changed).poll(&mut cx).is_pending());
+        atomic_arc.store(1.into());
+        assert_eq!(count.0.load(Relaxed), 1);
+        let Poll::Ready(arc) = Pin::new(&mut changed).poll(&mut cx) else {
+            panic!()
+        };
+        assert_eq!(**arc, 1);
+        atomic_arc.store(2.into());
+        assert_eq!(count.0.load(Relaxed), 1);
+    }
+
+    #[test]
+    fn changes() {
+        domain!(TestDomain(1));
+        let atomic_arc = AtomicArc::<usize, TestDomain>::from(0);
+        let count = Arc::new(CountWaker::default());
+        let waker = Waker::from(count.clone());
+        let mut cx = Context::from_waker(&waker);
+        let mut changes = atomic_arc.changes();
+        assert!(changes.poll_change(&mut cx).is_pending());
+        atomic_arc.store(1.into());
+        atomic_arc.store(2.into());
+        assert_eq!(count.0.load(Relaxed), 1);
+        assert_eq!(changes.poll_change(&mut cx).map(|a| *a), Poll::Ready(2));
+        assert!(changes.poll_change(&mut cx).is_pending());
+        drop(changes);
+        atomic_arc.store(3.into());
+        assert_eq!(count.0.load(Relaxed), 1);
+    }
+}
@@ -0,0 +1,194 @@
+//! Atomic storage for a pair of Arcs updated together.
+//!
+//! Double-width compare-and-swap is not available on all platforms, and the borrow slots of the
+//! domains protect single pointers, so it is not used. The pair is instead stored in its own
+//! allocation behind a single pointer, so it is updated with the regular write path — every
+//! write allocating the new pair — and loaded wait-free with a single borrow slot on every
+//! platform.
+
+use alloc::sync::Arc;
+use core::fmt;
+
+use crate::{domain::Domain, write_policy::WritePolicy, ArcBorrow, AtomicArc};
+
+/// A borrowed pair of Arcs, returned by [`AtomicArcPair::load`].
+///
+/// Both Arcs are projected from a single borrow of the pair, without being cloned.
+#[must_use]
+pub struct PairBorrow<T, U>(ArcBorrow<(Arc<T>, Arc<U>)>);
+
+impl<T, U> PairBorrow<T, U> {
+    /// Returns the first Arc.
+    #[inline]
+    pub fn first(&self) -> &Arc<T> {
+        &self.0 .0
+    }
+
+    /// Returns the second Arc.
+    #[inline]
+    pub fn second(&self) -> &Arc<U> {
+        &self.0 .1
+    }
+
+    /// Returns both Arcs.
+    #[inline]
+    pub fn get(&self) -> (&Arc<T>, &Arc<U>) {
+        (self.first(), self.second())
+    }
+
+    /// Converts the borrow into the owned pair of Arcs.
+    ///
+    /// The Arcs are cloned.
+    #[inline]
+    pub fn into_owned(self) -> (Arc<T>, Arc<U>) {
+        (self.first().clone(), self.second().clone())
+    }
+}
+
+impl<T: fmt::Debug, U: fmt::Debug> fmt::Debug for PairBorrow<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PairBorrow")
+            .field(&**self.first())
+            .field(&**self.second())
+            .finish()
+    }
+}
+
+/// An atomic storage for a pair of Arcs, whose loads always see matching Arcs.
+///
+/// # Examples
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// # use hazarc::pair::AtomicArcPair;
+/// # hazarc::domain!(Domain(8));
+/// let backends = AtomicArcPair::<_, _, Domain>::new(Arc::new("primary"), Arc::new("fallback"));
+/// let pair = backends.load();
+/// let (primary, fallback) = pair.get();
+/// backends.store((Arc::new("primary2"), Arc::new("fallback2")));
+/// assert!(backends
+///     .compare_exchange((primary, fallback), (primary.clone(), fallback.clone()))
+///     .is_err());
+/// let pair = backends.load_pair();
+/// assert_eq!((*pair.0, *pair.1), ("primary2", "fallback2"));
+/// ```
+#[cfg(feature = "default-domain")]
+pub struct AtomicArcPair<
+    T,
+    U,
+    D: Domain = crate::DefaultDomain,
+    W: WritePolicy = crate::write_policy::Concurrent,
+>(AtomicArc<(Arc<T>, Arc<U>), D, W>);
+/// An atomic storage for a pair of Arcs, whose loads always see matching Arcs.
+#[cfg(not(feature = "default-domain"))]
+pub struct AtomicArcPair<T, U, D: Domain, W: WritePolicy = crate::write_policy::Concurrent>(
+    AtomicArc<(Arc<T>, Arc<U>), D, W>,
+);
+
+impl<T, U, D: Domain, W: WritePolicy> AtomicArcPair<T, U, D, W> {
+    /// Constructs a new Arc pair atomic storage.
+    pub fn new(first: Arc<T>, second: Arc<U>) -> Self {
+        Self(AtomicArc::from(Arc::new((first, second))))
+    }
+
+    /// Loads the borrowed pair, without cloning its Arcs.
+    #[inline]
+    pub fn load_pair(&self) -> ArcBorrow<(Arc<T>, Arc<U>)> {
+        self.0.load()
+    }
+
+    /// Loads the pair of Arcs.
+    ///
+    /// Both Arcs are borrowed from the loaded pair, without being cloned.
+    #[inline]
+    pub fn load(&self) -> PairBorrow<T, U> {
+        PairBorrow(self.load_pair())
+    }
+
+    /// Stores the pair of Arcs and returns the previous one.
+    pub fn swap(&self, (first, second): (Arc<T>, Arc<U>)) -> (Arc<T>, Arc<U>) {
+        Self::into_arcs(self.0.swap(Arc::new((first, second))))
+    }
+
+    /// Stores the pair of Arcs.
+    pub fn store(&self, (first, second): (Arc<T>, Arc<U>)) {
+        self.0.store(Arc::new((first, second)));
+    }
+
+    /// Stores the new pair of Arcs if the current one matches the arguments.
+    ///
+    /// Returns the previous pair if store succeeds, or loads the current pair otherwise.
+    pub fn compare_exchange(
+        &self,
+        (first, second): (&Arc<T>, &Arc<U>),
+        (new_first, new_second): (Arc<T>, Arc<U>),
+    ) -> Result<(Arc<T>, Arc<U>), PairBorrow<T, U>> {
+        let new = Arc::new((new_first, new_second));
+        let mut current = self.load_pair();
+        loop {
+            if !Arc::ptr_eq(&current.0, first) || !Arc::ptr_eq(&current.1, second) {
+                return Err(PairBorrow(current));
+            }
+            // the pair may have been replaced by an equal one
+            match self.0.compare_exchange(&*current, new.clone()) {
+                Ok(old_pair) => return Ok(Self::into_arcs(old_pair)),
+                Err(pair) => current = pair,
+            }
+        }
+    }
+
+    fn into_arcs(pair: Arc<(Arc<T>, Arc<U>)>) -> (Arc<T>, Arc<U>) {
+        Arc::try_unwrap(pair).unwrap_or_else(|pair| (pair.0.clone(), pair.1.clone()))
+    }
+
+    /// Consumes the atomic storage and returns the stored pair of Arcs.
+    #[inline]
+    pub fn into_owned(self) -> (Arc<T>, Arc<U>) {
+        Self::into_arcs(self.0.into_owned())
+    }
+}
+
+impl<T: fmt::Debug, U: fmt::Debug, D: Domain, W: WritePolicy> fmt::Debug
+    for AtomicArcPair<T, U, D, W>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pair = self.load_pair();
+        f.debug_tuple("AtomicArcPair")
+            .field(&*pair.0)
+            .field(&*pair.1)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::sync::Arc;
+
+    use crate::{domain, pair::AtomicArcPair, write_policy::Concurrent};
+
+    #[test]
+    fn pair() {
+        domain!(TestDomain(1));
+        let (a, b) = (Arc::new(0), Arc::new(1));
+        let pair = AtomicArcPair::<usize, usize, TestDomain, Concurrent>::new(a.clone(), b.clone());
+        let borrow = pair.load();
+        assert_eq!((**borrow.first(), **borrow.second()), (0, 1));
+        // the Arcs are borrowed, not cloned
+        assert_eq!((Arc::strong_count(&a), Arc::strong_count(&b)), (2, 2));
+        drop(borrow);
+        let (x, y) = pair
+            .compare_exchange((&a, &b), (Arc::new(2), b.clone()))
+            .unwrap();
+        assert!(Arc::ptr_eq(&x, &a) && Arc::ptr_eq(&y, &b));
+        drop((x, y));
+        let current = pair
+            .compare_exchange((&a, &b), (a.clone(), b.clone()))
+            .unwrap_err();
+        assert_eq!((**current.first(), **current.second()), (2, 1));
+        assert_eq!(*pair.swap((a.clone(), b.clone())).0, 2);
+        drop(current);
+        let (x, y) = pair.into_owned();
+        assert!(Arc::ptr_eq(&x, &a) && Arc::ptr_eq(&y, &b));
+        assert_eq!(Arc::strong_count(&a), 2);
+    }
+}
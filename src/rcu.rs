@@ -0,0 +1,370 @@
+//! Read-copy-update maps built on atomic Arc storage.
+//!
+//! Reads are made on a borrowed snapshot of the map, and writes clone the map, modify the clone
+//! and publish it with a compare-and-swap, retrying on concurrent writes.
+
+use alloc::{boxed::Box, collections::BTreeMap, sync::Arc, vec::Vec};
+#[cfg(feature = "std")]
+use core::hash::{BuildHasher, Hash};
+use core::{borrow::Borrow, fmt};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use crate::{domain::Domain, write_policy::WritePolicy, ArcBorrow, AtomicArc};
+
+/// A map which can be used in a [`RcuMap`].
+pub trait Map: Clone {
+    /// The key type.
+    type Key;
+    /// The value type.
+    type Value;
+    /// Inserts the key-value pair, and returns the previous value.
+    fn insert(&mut self, key: Self::Key, value: Self::Value) -> Option<Self::Value>;
+}
+
+/// A [`Map`] whose entries can be looked up with `Q`, a borrowed form of its key.
+pub trait MapLookup<Q: ?Sized>: Map {
+    /// Returns `true` if the map contains the key.
+    fn contains_key(&self, key: &Q) -> bool;
+    /// Removes the key, and returns its value.
+    fn remove(&mut self, key: &Q) -> Option<Self::Value>;
+}
+
+impl<K: Ord + Clone, V: Clone> Map for BTreeMap<K, V> {
+    type Key = K;
+    type Value = V;
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert(key, value)
+    }
+}
+
+impl<K: Ord + Clone + Borrow<Q>, V: Clone, Q: ?Sized + Ord> MapLookup<Q> for BTreeMap<K, V> {
+    fn contains_key(&self, key: &Q) -> bool {
+        self.contains_key(key)
+    }
+    fn remove(&mut self, key: &Q) -> Option<V> {
+        self.remove(key)
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<K: Hash + Eq + Clone, V: Clone, S: BuildHasher + Clone> Map for HashMap<K, V, S> {
+    type Key = K;
+    type Value = V;
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert(key, value)
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<K, V, S, Q> MapLookup<Q> for HashMap<K, V, S>
+where
+    K: Hash + Eq + Clone + Borrow<Q>,
+    V: Clone,
+    S: BuildHasher + Clone,
+    Q: ?Sized + Hash + Eq,
+{
+    fn contains_key(&self, key: &Q) -> bool {
+        self.contains_key(key)
+    }
+    fn remove(&mut self, key: &Q) -> Option<V> {
+        self.remove(key)
+    }
+}
+
+/// A read-copy-update map.
+///
+/// Reads are wait-free, on the snapshot returned by [`load`](Self::load), which dereferences
+/// to the map. Every write publishes a modified clone of the map, so writes should be grouped
+/// with [`extend`](Self::extend) or [`batch`](Self::batch) to be published at once.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hazarc::rcu::RcuBTreeMap;
+/// # hazarc::domain!(Domain(8));
+/// let routes = RcuBTreeMap::<&str, u16, Domain>::default();
+/// routes.insert("api", 8080);
+/// let mut batch = routes.batch();
+/// batch.insert("web", 80);
+/// batch.remove("api");
+/// batch.commit();
+/// let snapshot = routes.load();
+/// assert_eq!(snapshot.get("web"), Some(&80));
+/// assert_eq!(snapshot.iter().count(), 1);
+/// ```
+#[cfg(feature = "default-domain")]
+pub struct RcuMap<
+    M,
+    D: Domain = crate::DefaultDomain,
+    W: WritePolicy = crate::write_policy::Concurrent,
+>(AtomicArc<M, D, W>);
+/// A read-copy-update map.
+///
+/// Reads are wait-free, on the snapshot returned by [`load`](Self::load), which dereferences
+/// to the map. Every write publishes a modified clone of the map, so writes should be grouped
+/// with [`extend`](Self::extend) or [`batch`](Self::batch) to be published at once.
+#[cfg(not(feature = "default-domain"))]
+pub struct RcuMap<M, D: Domain, W: WritePolicy = crate::write_policy::Concurrent>(
+    AtomicArc<M, D, W>,
+);
+
+/// Alias for `RcuMap<BTreeMap<K, V>>`
+#[cfg(feature = "default-domain")]
+pub type RcuBTreeMap<K, V, D = crate::DefaultDomain, W = crate::write_policy::Concurrent> =
+    RcuMap<BTreeMap<K, V>, D, W>;
+/// Alias for `RcuMap<BTreeMap<K, V>>`
+#[cfg(not(feature = "default-domain"))]
+pub type RcuBTreeMap<K, V, D, W = crate::write_policy::Concurrent> = RcuMap<BTreeMap<K, V>, D, W>;
+/// Alias for `RcuMap<HashMap<K, V>>`
+#[cfg(feature = "default-domain")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub type RcuHashMap<K, V, D = crate::DefaultDomain, W = crate::write_policy::Concurrent> =
+    RcuMap<HashMap<K, V>, D, W>;
+/// Alias for `RcuMap<HashMap<K, V>>`
+#[cfg(all(feature = "std", not(feature = "default-domain")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub type RcuHashMap<K, V, D, W = crate::write_policy::Concurrent> = RcuMap<HashMap<K, V>, D, W>;
+
+impl<M: Map, D: Domain, W: WritePolicy> RcuMap<M, D, W> {
+    /// Constructs a new read-copy-update map.
+    pub fn new(map: M) -> Self {
+        Self(AtomicArc::new(Arc::new(map)))
+    }
+
+    /// Returns the underlying atomic storage.
+    #[inline]
+    pub fn inner(&self) -> &AtomicArc<M, D, W> {
+        &self.0
+    }
+
+    /// Loads a borrowed snapshot of the map.
+    #[inline]
+    pub fn load(&self) -> ArcBorrow<M> {
+        self.0.load()
+    }
+
+    /// Loads an owned snapshot of the map.
+    #[inline]
+    pub fn load_owned(&self) -> Arc<M> {
+        self.0.load_owned()
+    }
+
+    fn update<R>(&self, mut f: impl FnMut(&mut M) -> Option<R>) -> Option<R> {
+        let mut res = None;
+        let _ = self.0.fetch_update(|map| {
+            let mut map = M::clone(map);
+            res = f(&mut map);
+            res.is_some().then_some(map)
+        });
+        res
+    }
+
+    /// Inserts the key-value pair, and returns the previous value.
+    pub fn insert(&self, key: M::Key, value: M::Value) -> Option<M::Value>
+    where
+        M::Key: Clone,
+        M::Value: Clone,
+    {
+        self.update(|map| Some(map.insert(key.clone(), value.clone())))
+            .flatten()
+    }
+
+    /// Removes the key, and returns its value.
+    ///
+    /// The key may be any borrowed form of the map key type. Nothing is published if the map
+    /// doesn't contain the key.
+    pub fn remove<Q: ?Sized>(&self, key: &Q) -> Option<M::Value>
+    where
+        M: MapLookup<Q>,
+    {
+        let mut value = None;
+        let _ = self.0.fetch_update(|map| {
+            // avoid cloning the map for nothing
+            if !map.contains_key(key) {
+                value = None;
+                return None;
+            }
+            let mut map = M::clone(map);
+            value = map.remove(key);
+            Some(map)
+        });
+        value
+    }
+
+    /// Inserts all the key-value pairs with a single publish.
+    pub fn extend(&self, iter: impl IntoIterator<Item = (M::Key, M::Value)>)
+    where
+        M::Key: Clone,
+        M::Value: Clone,
+    {
+        let mut batch = self.batch();
+        batch.extend(iter);
+        batch.commit();
+    }
+
+    /// Returns an empty batch of writes, published at once by [`RcuMapBatch::commit`].
+    pub fn batch(&self) -> RcuMapBatch<'_, M, D, W> {
+        RcuMapBatch {
+            map: self,
+            writes: Vec::new(),
+        }
+    }
+}
+
+impl<M: Map + Default, D: Domain, W: WritePolicy> Default for RcuMap<M, D, W> {
+    fn default() -> Self {
+        Self::new(M::default())
+    }
+}
+
+impl<M: Map, D: Domain, W: WritePolicy> From<M> for RcuMap<M, D, W> {
+    fn from(value: M) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<M: fmt::Debug, D: Domain, W: WritePolicy> fmt::Debug for RcuMap<M, D, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RcuMap").field(&**self.0.load()).finish()
+    }
+}
+
+enum Write<'a, M: Map> {
+    Insert(M::Key, M::Value),
+    // returns `true` if the key was removed
+    Remove(Box<dyn Fn(&mut M) -> bool + 'a>),
+}
+
+/// A batch of writes of a [`RcuMap`], constructed with [`RcuMap::batch`].
+///
+/// Writes are buffered, and applied in order on a single clone of the map when committed,
+/// so they are published at once.
+#[must_use]
+pub struct RcuMapBatch<'a, M: Map, D: Domain, W: WritePolicy> {
+    map: &'a RcuMap<M, D, W>,
+    writes: Vec<Write<'a, M>>,
+}
+
+impl<'a, M: Map, D: Domain, W: WritePolicy> RcuMapBatch<'a, M, D, W> {
+    /// Adds the insertion of the key-value pair to the batch.
+    pub fn insert(&mut self, key: M::Key, value: M::Value) {
+        self.writes.push(Write::Insert(key, value));
+    }
+
+    /// Adds the removal of the key to the batch.
+    ///
+    /// The key may be any borrowed form of the map key type.
+    pub fn remove<Q: ?Sized>(&mut self, key: &'a Q)
+    where
+        M: MapLookup<Q>,
+    {
+        let remove = move |map: &mut M| map.remove(key).is_some();
+        self.writes.push(Write::Remove(Box::new(remove)));
+    }
+
+    /// Adds the insertion of all the key-value pairs to the batch.
+    pub fn extend(&mut self, iter: impl IntoIterator<Item = (M::Key, M::Value)>) {
+        (self.writes).extend(iter.into_iter().map(|(k, v)| Write::Insert(k, v)));
+    }
+
+    /// Returns the number of writes in the batch.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.writes.len()
+    }
+
+    /// Returns `true` if the batch contains no write.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty()
+    }
+
+    /// Publishes all the writes of the batch at once.
+    ///
+    /// Nothing is published if the writes leave the map unchanged, i.e. if the batch only
+    /// removes keys which are not in the map.
+    pub fn commit(self)
+    where
+        M::Key: Clone,
+        M::Value: Clone,
+    {
+        if self.writes.is_empty() {
+            return;
+        }
+        self.map.update(|map| {
+            let mut changed = false;
+            for write in &self.writes {
+                changed |= match write {
+                    Write::Insert(key, value) => {
+                        drop(map.insert(key.clone(), value.clone()));
+                        true
+                    }
+                    Write::Remove(remove) => remove(map),
+                };
+            }
+            changed.then_some(())
+        });
+    }
+}
+
+impl<M: Map, D: Domain, W: WritePolicy> fmt::Debug for RcuMapBatch<'_, M, D, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RcuMapBatch")
+            .field("writes", &self.writes.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        collections::BTreeMap,
+        string::{String, ToString},
+        sync::Arc,
+    };
+
+    use crate::{domain, rcu::RcuMap, write_policy::Concurrent};
+
+    #[test]
+    fn rcu_map() {
+        domain!(TestDomain(1));
+        let map = RcuMap::<BTreeMap<usize, usize>, TestDomain, Concurrent>::default();
+        let snapshot = map.load_owned();
+        assert_eq!(map.insert(0, 0), None);
+        assert_eq!(map.insert(0, 1), Some(0));
+        assert_eq!(map.remove(&1), None);
+        map.extend([(1, 1), (2, 2)]);
+        let mut batch = map.batch();
+        batch.remove(&0);
+        batch.insert(3, 3);
+        batch.insert(1, 4);
+        batch.commit();
+        assert!(snapshot.is_empty());
+        let entries: alloc::vec::Vec<_> = map.load().iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(entries, [(1, 4), (2, 2), (3, 3)]);
+        assert_eq!(map.remove(&2), Some(2));
+        // a batch of no-op removals publishes nothing
+        let current = map.load_owned();
+        let mut batch = map.batch();
+        batch.remove(&0);
+        batch.remove(&2);
+        batch.commit();
+        assert!(Arc::ptr_eq(&map.load_owned(), &current));
+    }
+
+    #[test]
+    fn borrowed_keys() {
+        domain!(TestDomain(1));
+        let map = RcuMap::<BTreeMap<String, usize>, TestDomain, Concurrent>::default();
+        map.insert("foo".to_string(), 0);
+        map.insert("bar".to_string(), 1);
+        assert_eq!(map.remove("foo"), Some(0));
+        let mut batch = map.batch();
+        batch.remove("bar");
+        batch.commit();
+        assert!(map.load().is_empty());
+    }
+}
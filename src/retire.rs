@@ -0,0 +1,247 @@
+//! Atomic Arc storage running a callback when a replaced value is released.
+//!
+//! Values are stored alongside the callback in a [`Retiring`] wrapper, which runs it on drop
+//! once it has been armed, i.e. once the value has been stored. As borrows and clones of a
+//! replaced Arc hold a reference — ownership being transferred to outstanding borrows when the
+//! Arc is swapped — the callback runs exactly once the last of them is released, in whichever
+//! thread releases it.
+
+use alloc::sync::Arc;
+use core::{
+    fmt,
+    ops::Deref,
+    sync::atomic::{AtomicBool, Ordering::Relaxed},
+};
+
+use crate::{arc::ArcRef, domain::Domain, write_policy::WritePolicy, ArcBorrow, AtomicArc};
+
+/// A value which runs its retire callback when dropped, if it has been armed.
+pub struct Retiring<T, F: Fn(&mut T)> {
+    value: T,
+    on_retire: Arc<F>,
+    armed: AtomicBool,
+}
+
+impl<T, F: Fn(&mut T)> Retiring<T, F> {
+    /// Arms the retire callback, which then runs when the value is dropped.
+    ///
+    /// Values are armed when they are stored by [`RetiringAtomicArc`]; this is only needed for
+    /// values stored directly in its [inner](RetiringAtomicArc::inner) storage.
+    #[inline]
+    pub fn arm(this: &Self) {
+        // the store is ordered before the drop by the release decrement of the Arc count
+        this.armed.store(true, Relaxed);
+    }
+}
+
+impl<T, F: Fn(&mut T)> Deref for Retiring<T, F> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<T, F: Fn(&mut T)> Drop for Retiring<T, F> {
+    fn drop(&mut self) {
+        if *self.armed.get_mut() {
+            (self.on_retire)(&mut self.value);
+        }
+    }
+}
+
+impl<T: fmt::Debug, F: Fn(&mut T)> fmt::Debug for Retiring<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+/// An [`AtomicArc`] running a callback when a value is released.
+///
+/// The callback is called with the value when the last borrow or clone of its Arc is dropped,
+/// after it has been replaced by a write, or when the storage itself is dropped. It can be used
+/// to log that a value is fully drained, or to zeroize secrets.
+///
+/// The callback is only armed once the value is stored, so values which are never stored, e.g.
+/// the new value of a failed [`compare_exchange`](Self::compare_exchange), are dropped without
+/// calling it. Values returned by [`swap`](Self::swap) have been stored, so the callback still
+/// runs when the returned Arc is dropped.
+///
+/// # Examples
+///
+/// ```rust
+/// # use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+/// # use hazarc::retire::RetiringAtomicArc;
+/// # hazarc::domain!(Domain(8));
+/// static RETIRED: AtomicUsize = AtomicUsize::new(0);
+/// let atomic_arc = RetiringAtomicArc::<_, Domain>::new(0, |v: &mut usize| {
+///     RETIRED.store(*v, Relaxed);
+/// });
+/// atomic_arc.store(1);
+/// let borrow = atomic_arc.load();
+/// atomic_arc.store(2);
+/// assert_eq!(RETIRED.load(Relaxed), 0);
+/// drop(borrow);
+/// assert_eq!(RETIRED.load(Relaxed), 1);
+/// ```
+#[cfg(feature = "default-domain")]
+pub struct RetiringAtomicArc<
+    T,
+    D: Domain = crate::DefaultDomain,
+    W: WritePolicy = crate::write_policy::Concurrent,
+    F: Fn(&mut T) = fn(&mut T),
+> {
+    inner: AtomicArc<Retiring<T, F>, D, W>,
+    on_retire: Arc<F>,
+}
+/// An [`AtomicArc`] running a callback when a value is released.
+///
+/// The callback is called with the value when the last borrow or clone of its Arc is dropped,
+/// after it has been replaced by a write, or when the storage itself is dropped. It can be used
+/// to log that a value is fully drained, or to zeroize secrets.
+///
+/// The callback is only armed once the value is stored, so values which are never stored, e.g.
+/// the new value of a failed [`compare_exchange`](Self::compare_exchange), are dropped without
+/// calling it. Values returned by [`swap`](Self::swap) have been stored, so the callback still
+/// runs when the returned Arc is dropped.
+#[cfg(not(feature = "default-domain"))]
+pub struct RetiringAtomicArc<
+    T,
+    D: Domain,
+    W: WritePolicy = crate::write_policy::Concurrent,
+    F: Fn(&mut T) = fn(&mut T),
+> {
+    inner: AtomicArc<Retiring<T, F>, D, W>,
+    on_retire: Arc<F>,
+}
+
+impl<T, D: Domain, W: WritePolicy, F: Fn(&mut T)> RetiringAtomicArc<T, D, W, F> {
+    /// Constructs a new Arc atomic storage, with the given retire callback.
+    pub fn new(value: T, on_retire: F) -> Self {
+        let on_retire = Arc::new(on_retire);
+        let inner = AtomicArc::new(Arc::new(Retiring {
+            value,
+            on_retire: on_retire.clone(),
+            armed: AtomicBool::new(true),
+        }));
+        Self { inner, on_retire }
+    }
+
+    /// Wraps the value into an Arc holding the retire callback of this storage.
+    ///
+    /// It can be used with the operations of the [inner](Self::inner) storage; the callback must
+    /// then be [armed](Retiring::arm) once the value is stored.
+    pub fn retiring(&self, value: T) -> Arc<Retiring<T, F>> {
+        Arc::new(Retiring {
+            value,
+            on_retire: self.on_retire.clone(),
+            armed: AtomicBool::new(false),
+        })
+    }
+
+    fn armed(&self, value: T) -> Arc<Retiring<T, F>> {
+        let arc = self.retiring(value);
+        Retiring::arm(&arc);
+        arc
+    }
+
+    /// Returns the inner atomic storage.
+    #[inline]
+    pub fn inner(&self) -> &AtomicArc<Retiring<T, F>, D, W> {
+        &self.inner
+    }
+
+    /// Loads a borrowed Arc.
+    #[inline]
+    pub fn load(&self) -> ArcBorrow<Retiring<T, F>> {
+        self.inner.load()
+    }
+
+    /// Loads an owned Arc.
+    #[inline]
+    pub fn load_owned(&self) -> Arc<Retiring<T, F>> {
+        self.inner.load_owned()
+    }
+
+    /// Stores the value and returns the previous Arc.
+    ///
+    /// The retire callback is called with the previous value when the returned Arc, and all
+    /// its outstanding borrows and clones, are dropped.
+    pub fn swap(&self, value: T) -> Arc<Retiring<T, F>> {
+        self.inner.swap(self.armed(value))
+    }
+
+    /// Stores the value.
+    ///
+    /// The retire callback is called with the previous value when all its outstanding borrows
+    /// and clones are dropped, or immediately if there are none.
+    pub fn store(&self, value: T) {
+        self.inner.store(self.armed(value));
+    }
+
+    /// Stores the value if the current Arc is the same as `current`.
+    ///
+    /// Returns the previous Arc if store succeeds, or loads the current Arc otherwise; the value
+    /// is then dropped without calling the retire callback.
+    #[allow(clippy::type_complexity)]
+    pub fn compare_exchange<C: ArcRef<Arc<Retiring<T, F>>>>(
+        &self,
+        current: C,
+        value: T,
+    ) -> Result<Arc<Retiring<T, F>>, ArcBorrow<Retiring<T, F>>> {
+        let new = self.retiring(value);
+        let res = self.inner.compare_exchange(current, new.clone());
+        if res.is_ok() {
+            Retiring::arm(&new);
+        }
+        res
+    }
+}
+
+impl<T: fmt::Debug, D: Domain, W: WritePolicy, F: Fn(&mut T)> fmt::Debug
+    for RetiringAtomicArc<T, D, W, F>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RetiringAtomicArc")
+            .field(&**self.load())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+
+    use crate::{domain, retire::RetiringAtomicArc, write_policy::Concurrent};
+
+    #[test]
+    fn on_retire() {
+        domain!(TestDomain(1));
+        let retired_cell = RefCell::new(Vec::new());
+        let retired = || retired_cell.borrow().clone();
+        let on_retire = |v: &mut usize| retired_cell.borrow_mut().push(*v);
+        let atomic_arc = RetiringAtomicArc::<_, TestDomain, Concurrent, _>::new(0, on_retire);
+        atomic_arc.store(1);
+        assert_eq!(retired(), [0]);
+        let borrow = atomic_arc.load();
+        let clone = atomic_arc.load();
+        assert_eq!(***clone, 1);
+        let old = atomic_arc.swap(2);
+        drop(borrow);
+        drop(old);
+        assert_eq!(retired(), [0]);
+        drop(clone);
+        assert_eq!(retired(), [0, 1]);
+        let stale = atomic_arc.retiring(3);
+        assert!(atomic_arc.compare_exchange(&stale, 4).is_err());
+        drop(stale);
+        assert_eq!(retired(), [0, 1]);
+        let current = atomic_arc.load_owned();
+        assert!(atomic_arc.compare_exchange(&current, 5).is_ok());
+        drop(current);
+        assert_eq!(retired(), [0, 1, 2]);
+        drop(atomic_arc);
+        assert_eq!(retired(), [0, 1, 2, 5]);
+    }
+}
@@ -0,0 +1,279 @@
+//! Concurrent map sharding its entries, whose values are individual atomic Arc storages.
+
+use alloc::{sync::Arc, vec::Vec};
+use core::{
+    borrow::Borrow,
+    fmt,
+    hash::{BuildHasher, Hash, Hasher},
+};
+use std::collections::{hash_map::RandomState, HashMap};
+
+use crate::{
+    domain::Domain,
+    write_policy::{Concurrent, WritePolicy},
+    ArcBorrow, AtomicArc,
+};
+
+const DEFAULT_SHARD_COUNT: usize = 64;
+
+type Shard<K, V, D, W, S> = AtomicArc<HashMap<K, Arc<AtomicArc<V, D, W>>, S>, D, Concurrent>;
+
+/// A concurrent map, whose values are individual [`AtomicArc`]s distributed in a fixed number of
+/// shards.
+///
+/// Values are loaded and written like an `AtomicArc`, with the same wait-free reads, and the
+/// shard storing them is only republished by structural changes, i.e. insertion of a new key
+/// or removal. Each shard is a read-copy-update map, so these changes copy only the shard.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hazarc::sharded::ShardedMap;
+/// # hazarc::domain!(Domain(8));
+/// let sessions = ShardedMap::<u64, &str, Domain>::new();
+/// sessions.insert(42, "alice".into());
+/// let session = sessions.get(&42).unwrap();
+/// sessions.update(&42, |name| (**name == "alice").then_some("bob"));
+/// assert_eq!((**session, **sessions.get(&42).unwrap()), ("alice", "bob"));
+/// assert_eq!(*sessions.remove(&42).unwrap(), "bob");
+/// assert!(sessions.get(&42).is_none());
+/// ```
+#[cfg(feature = "default-domain")]
+pub struct ShardedMap<
+    K,
+    V,
+    D: Domain = crate::DefaultDomain,
+    W: WritePolicy = crate::write_policy::Concurrent,
+    S = RandomState,
+> {
+    shards: Vec<Shard<K, V, D, W, S>>,
+    hasher: S,
+}
+/// A concurrent map, whose values are individual [`AtomicArc`]s distributed in a fixed number of
+/// shards.
+///
+/// Values are loaded and written like an `AtomicArc`, with the same wait-free reads, and the
+/// shard storing them is only republished by structural changes, i.e. insertion of a new key
+/// or removal. Each shard is a read-copy-update map, so these changes copy only the shard.
+#[cfg(not(feature = "default-domain"))]
+pub struct ShardedMap<
+    K,
+    V,
+    D: Domain,
+    W: WritePolicy = crate::write_policy::Concurrent,
+    S = RandomState,
+> {
+    shards: Vec<Shard<K, V, D, W, S>>,
+    hasher: S,
+}
+
+impl<K, V, D: Domain, W: WritePolicy> ShardedMap<K, V, D, W> {
+    /// Constructs a new empty map, with a default number of shards.
+    pub fn new() -> Self {
+        Self::with_shard_count(DEFAULT_SHARD_COUNT)
+    }
+
+    /// Constructs a new empty map, with the given number of shards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shard_count` is zero.
+    pub fn with_shard_count(shard_count: usize) -> Self {
+        Self::with_shard_count_and_hasher(shard_count, RandomState::new())
+    }
+}
+
+impl<K, V, D: Domain, W: WritePolicy, S: BuildHasher + Clone> ShardedMap<K, V, D, W, S> {
+    /// Constructs a new empty map, with the given number of shards and hasher.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shard_count` is zero.
+    pub fn with_shard_count_and_hasher(shard_count: usize, hasher: S) -> Self {
+        assert!(shard_count > 0, "ShardedMap requires at least one shard");
+        let shards = (0..shard_count)
+            .map(|_| AtomicArc::from(HashMap::with_hasher(hasher.clone())))
+            .collect();
+        Self { shards, hasher }
+    }
+
+    /// Returns the number of shards.
+    #[inline]
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    fn shard<Q: Hash + ?Sized>(&self, key: &Q) -> &Shard<K, V, D, W, S> {
+        let mut hasher = self.hasher.build_hasher();
+        key.hash(&mut hasher);
+        // shard maps use the same hasher, and their buckets are indexed with the low bits
+        let idx = (hasher.finish() >> 32) as usize % self.shards.len();
+        &self.shards[idx]
+    }
+
+    /// Returns the number of entries.
+    ///
+    /// Shards are loaded one after the other, so the result may be inconsistent with concurrent
+    /// structural changes.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.load().len()).sum()
+    }
+
+    /// Returns `true` if the map contains no entry.
+    ///
+    /// See [`len`](Self::len).
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| shard.load().is_empty())
+    }
+
+    /// Returns `true` if the map contains the key.
+    pub fn contains_key<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q> + Hash + Eq,
+    {
+        self.shard(key).load().contains_key(key)
+    }
+
+    /// Loads a borrowed Arc of the value of the key.
+    pub fn get<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<ArcBorrow<V>>
+    where
+        K: Borrow<Q> + Hash + Eq,
+    {
+        Some(self.shard(key).load().get(key)?.load())
+    }
+
+    /// Fetches the value of the key, applies a function on it and tries to store the result if
+    /// the value has not changed, with the value compare-and-swap.
+    ///
+    /// Returns `None` if the map doesn't contain the key. See
+    /// [`AtomicArc::fetch_update`](crate::atomic::AtomicArcPtr::fetch_update); an update
+    /// concurrent to the removal of the key may be lost.
+    pub fn update<Q: Hash + Eq + ?Sized, F: FnMut(&Arc<V>) -> Option<R>, R: Into<Arc<V>>>(
+        &self,
+        key: &Q,
+        f: F,
+    ) -> Option<Result<Arc<V>, ArcBorrow<V>>>
+    where
+        K: Borrow<Q> + Hash + Eq,
+    {
+        Some(self.shard(key).load().get(key)?.fetch_update(f))
+    }
+
+    /// Stores the value of the key, and returns the previous one.
+    ///
+    /// The shard is only republished if the map doesn't contain the key. If the key is removed
+    /// concurrently, the value is stored again until it is not stored in a removed entry, so it
+    /// may be both returned by the removal and stored after it.
+    pub fn insert(&self, key: K, value: Arc<V>) -> Option<Arc<V>>
+    where
+        K: Hash + Eq + Clone,
+    {
+        let shard = self.shard(&key);
+        let mut current = shard.load();
+        let mut new_cell = None;
+        loop {
+            if let Some(cell) = current.get(&key) {
+                let old = cell.swap(value.clone());
+                // the entry may have been removed before the swap, losing the value
+                let reloaded = shard.load();
+                if reloaded.get(&key).map_or(false, |c| Arc::ptr_eq(c, cell)) {
+                    return Some(old);
+                }
+                current = reloaded;
+                continue;
+            }
+            let cell = new_cell.get_or_insert_with(|| Arc::new(AtomicArc::new(value.clone())));
+            let mut map = HashMap::clone(&current);
+            map.insert(key.clone(), cell.clone());
+            match shard.compare_exchange(&*current, Arc::new(map)) {
+                Ok(_) => return None,
+                Err(map) => current = map,
+            }
+        }
+    }
+
+    /// Removes the key, and returns its value.
+    pub fn remove<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<Arc<V>>
+    where
+        K: Borrow<Q> + Hash + Eq + Clone,
+    {
+        let shard = self.shard(key);
+        let mut current = shard.load();
+        loop {
+            if !current.contains_key(key) {
+                return None;
+            }
+            let mut map = HashMap::clone(&current);
+            let cell = map.remove(key).unwrap();
+            match shard.compare_exchange(&*current, Arc::new(map)) {
+                Ok(old_map) => {
+                    drop((old_map, current));
+                    return Some(match Arc::try_unwrap(cell) {
+                        Ok(cell) => cell.into_owned(),
+                        Err(cell) => cell.load_owned(),
+                    });
+                }
+                Err(map) => current = map,
+            }
+        }
+    }
+}
+
+impl<K, V, D: Domain, W: WritePolicy> Default for ShardedMap<K, V, D, W> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, D: Domain, W: WritePolicy, S> Drop for ShardedMap<K, V, D, W, S> {
+    fn drop(&mut self) {
+        // reclaim all the shards, then drop all the values, with a single pass over the domain
+        // nodes each
+        let cells = (AtomicArc::into_owned_all(self.shards.drain(..)).into_iter())
+            .filter_map(|map| Arc::try_unwrap(map).ok())
+            .flat_map(|map| map.into_values())
+            .filter_map(|cell| Arc::try_unwrap(cell).ok());
+        AtomicArc::drop_all(cells);
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, D: Domain, W: WritePolicy, S> fmt::Debug
+    for ShardedMap<K, V, D, W, S>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut map = f.debug_map();
+        for shard in &self.shards {
+            for (key, cell) in shard.load().iter() {
+                map.entry(key, &**cell.load());
+            }
+        }
+        map.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::sync::Arc;
+
+    use crate::{domain, sharded::ShardedMap, write_policy::Concurrent};
+
+    #[test]
+    fn sharded_map() {
+        domain!(TestDomain(1));
+        let map = ShardedMap::<usize, usize, TestDomain, Concurrent>::with_shard_count(2);
+        let values: alloc::vec::Vec<_> = (0..4).map(Arc::new).collect();
+        for (i, value) in values.iter().enumerate() {
+            assert!(map.insert(i, value.clone()).is_none());
+        }
+        assert_eq!(map.len(), 4);
+        assert!(Arc::ptr_eq(&map.insert(0, 4.into()).unwrap(), &values[0]));
+        let borrow = map.get(&1).unwrap();
+        assert!(map.update(&1, |v| Some(**v + 4)).unwrap().is_ok());
+        assert!(map.update(&5, |v| Some(**v)).is_none());
+        assert!(Arc::ptr_eq(&map.remove(&2).unwrap(), &values[2]));
+        assert!(map.remove(&2).is_none() && !map.contains_key(&2));
+        assert_eq!((**borrow, **map.get(&1).unwrap()), (1, 5));
+        drop((borrow, map));
+        assert!(values.iter().all(|value| Arc::strong_count(value) == 1));
+    }
+}
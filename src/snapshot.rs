@@ -0,0 +1,213 @@
+//! Consistent snapshots of several atomic Arc storages.
+//!
+//! [`snapshot`](snapshot()) loads the Arcs and checks that the storages still hold them,
+//! retrying otherwise, so it works whatever the way the storages are written.
+//!
+//! A [`SnapshotGroup`] is a sequence lock shared by related storages: writes through the group
+//! make the sequence odd while they are in progress, and reads are retried if the sequence has
+//! changed while they were loading the Arcs. Writes made outside the group are not detected.
+
+use core::{
+    fmt,
+    sync::atomic::{
+        AtomicUsize,
+        Ordering::{Relaxed, SeqCst},
+    },
+};
+
+use crossbeam_utils::Backoff;
+
+use crate::{
+    arc::ArcPtr,
+    atomic::{ArcPtrBorrow, AtomicArcPtr},
+    domain::Domain,
+    write_policy::WritePolicy,
+};
+
+/// A tuple of references to atomic Arc storages, which can be read or written together with a
+/// [`SnapshotGroup`].
+pub trait AtomicArcTuple {
+    /// The tuple of the borrowed Arcs.
+    type Borrows;
+    /// The tuple of the owned Arcs.
+    type Arcs;
+    /// Loads all the Arcs.
+    fn load_all(&self) -> Self::Borrows;
+    /// Stores all the Arcs, and returns the previous ones.
+    fn swap_all(&self, arcs: Self::Arcs) -> Self::Arcs;
+    /// Returns `true` if all the storages still hold the borrowed Arcs.
+    fn is_current(&self, borrows: &Self::Borrows) -> bool;
+}
+
+macro_rules! atomic_arc_tuple {
+    ($($a:ident $d:ident $w:ident $i:tt),*) => {
+        impl<$($a: ArcPtr, $d: Domain, $w: WritePolicy),*> AtomicArcTuple
+            for ($(&AtomicArcPtr<$a, $d, $w>,)*)
+        {
+            type Borrows = ($(ArcPtrBorrow<$a>,)*);
+            type Arcs = ($($a,)*);
+            fn load_all(&self) -> Self::Borrows {
+                ($(self.$i.load(),)*)
+            }
+            fn swap_all(&self, arcs: Self::Arcs) -> Self::Arcs {
+                ($(self.$i.swap(arcs.$i),)*)
+            }
+            fn is_current(&self, borrows: &Self::Borrows) -> bool {
+                $(self.$i.load_ptr(SeqCst) == $a::as_ptr(&borrows.$i))&&*
+            }
+        }
+    };
+}
+
+atomic_arc_tuple!(A0 D0 W0 0);
+atomic_arc_tuple!(A0 D0 W0 0, A1 D1 W1 1);
+atomic_arc_tuple!(A0 D0 W0 0, A1 D1 W1 1, A2 D2 W2 2);
+atomic_arc_tuple!(A0 D0 W0 0, A1 D1 W1 1, A2 D2 W2 2, A3 D3 W3 3);
+atomic_arc_tuple!(A0 D0 W0 0, A1 D1 W1 1, A2 D2 W2 2, A3 D3 W3 3, A4 D4 W4 4);
+atomic_arc_tuple!(A0 D0 W0 0, A1 D1 W1 1, A2 D2 W2 2, A3 D3 W3 3, A4 D4 W4 4, A5 D5 W5 5);
+atomic_arc_tuple!(
+    A0 D0 W0 0, A1 D1 W1 1, A2 D2 W2 2, A3 D3 W3 3, A4 D4 W4 4, A5 D5 W5 5, A6 D6 W6 6
+);
+atomic_arc_tuple!(
+    A0 D0 W0 0, A1 D1 W1 1, A2 D2 W2 2, A3 D3 W3 3, A4 D4 W4 4, A5 D5 W5 5, A6 D6 W6 6,
+    A7 D7 W7 7
+);
+atomic_arc_tuple!(
+    A0 D0 W0 0, A1 D1 W1 1, A2 D2 W2 2, A3 D3 W3 3, A4 D4 W4 4, A5 D5 W5 5, A6 D6 W6 6,
+    A7 D7 W7 7, A8 D8 W8 8
+);
+atomic_arc_tuple!(
+    A0 D0 W0 0, A1 D1 W1 1, A2 D2 W2 2, A3 D3 W3 3, A4 D4 W4 4, A5 D5 W5 5, A6 D6 W6 6,
+    A7 D7 W7 7, A8 D8 W8 8, A9 D9 W9 9
+);
+atomic_arc_tuple!(
+    A0 D0 W0 0, A1 D1 W1 1, A2 D2 W2 2, A3 D3 W3 3, A4 D4 W4 4, A5 D5 W5 5, A6 D6 W6 6,
+    A7 D7 W7 7, A8 D8 W8 8, A9 D9 W9 9, A10 D10 W10 10
+);
+atomic_arc_tuple!(
+    A0 D0 W0 0, A1 D1 W1 1, A2 D2 W2 2, A3 D3 W3 3, A4 D4 W4 4, A5 D5 W5 5, A6 D6 W6 6,
+    A7 D7 W7 7, A8 D8 W8 8, A9 D9 W9 9, A10 D10 W10 10, A11 D11 W11 11
+);
+
+/// Loads all the Arcs at a single point in time.
+///
+/// The Arcs are loaded, then every storage is checked to still hold its loaded Arc, and the
+/// loads are retried otherwise. The loaded Arcs are kept alive, so their addresses cannot be
+/// reused, and they were thus all current when the last one was loaded — provided a replaced
+/// Arc is not stored again in the same storage in the meantime. Contrary to
+/// [`SnapshotGroup::snapshot`], it doesn't require writes to go through a group, but it is
+/// retried on any write of the storages, so it is not wait-free, and several writes of a
+/// [`SnapshotGroup`] are not seen as a single one.
+///
+/// # Examples
+///
+/// ```rust
+/// # hazarc::domain!(Domain(8));
+/// # type AtomicArc<T> = hazarc::AtomicArc<T, Domain>;
+/// let (schema, routing) = (AtomicArc::<u64>::from(0), AtomicArc::<&str>::from("v0"));
+/// schema.store(1.into());
+/// let (schema, routing) = hazarc::snapshot((&schema, &routing));
+/// assert_eq!((**schema, **routing), (1, "v0"));
+/// ```
+pub fn snapshot<T: AtomicArcTuple>(atomics: T) -> T::Borrows {
+    let backoff = Backoff::new();
+    loop {
+        let borrows = atomics.load_all();
+        if atomics.is_current(&borrows) {
+            return borrows;
+        }
+        drop(borrows);
+        backoff.snooze();
+    }
+}
+
+/// A sequence lock making reads and writes of several atomic Arc storages consistent.
+///
+/// [`snapshot`](Self::snapshot) returns Arcs which were all stored at a single point in time,
+/// provided the storages are only written through [`store_all`](Self::store_all) or
+/// [`swap_all`](Self::swap_all) of the same group. Writes through the group are serialized,
+/// and snapshots are retried while a write is in progress, so they are no longer wait-free.
+///
+/// Writes made directly on the storages bypass the sequence lock, so they are not detected,
+/// and a snapshot may then mix Arcs stored before and after them. Use the
+/// [`snapshot`](snapshot()) function if the storages may be written outside the group.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hazarc::snapshot::SnapshotGroup;
+/// # hazarc::domain!(Domain(8));
+/// # type AtomicArc<T> = hazarc::AtomicArc<T, Domain>;
+/// static GROUP: SnapshotGroup = SnapshotGroup::new();
+/// let (schema, routing) = (AtomicArc::<u64>::from(0), AtomicArc::<&str>::from("v0"));
+/// GROUP.store_all((&schema, &routing), (1.into(), "v1".into()));
+/// let (schema, routing) = GROUP.snapshot((&schema, &routing));
+/// assert_eq!((**schema, **routing), (1, "v1"));
+/// ```
+pub struct SnapshotGroup {
+    seq: AtomicUsize,
+}
+
+impl SnapshotGroup {
+    /// Constructs a new snapshot group.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            seq: AtomicUsize::new(0),
+        }
+    }
+
+    /// Loads all the Arcs at a single point in time.
+    pub fn snapshot<T: AtomicArcTuple>(&self, atomics: T) -> T::Borrows {
+        let backoff = Backoff::new();
+        loop {
+            let seq = self.seq.load(SeqCst);
+            if seq & 1 == 0 {
+                let borrows = atomics.load_all();
+                if self.seq.load(SeqCst) == seq {
+                    return borrows;
+                }
+            }
+            backoff.snooze();
+        }
+    }
+
+    /// Stores all the Arcs at a single point in time, and returns the previous ones.
+    pub fn swap_all<T: AtomicArcTuple>(&self, atomics: T, arcs: T::Arcs) -> T::Arcs {
+        let backoff = Backoff::new();
+        let mut seq = self.seq.load(Relaxed);
+        loop {
+            if seq & 1 == 0 {
+                match (self.seq).compare_exchange_weak(seq, seq + 1, SeqCst, Relaxed) {
+                    Ok(_) => break,
+                    Err(s) => seq = s,
+                }
+            } else {
+                backoff.snooze();
+                seq = self.seq.load(Relaxed);
+            }
+        }
+        let old_arcs = atomics.swap_all(arcs);
+        self.seq.store(seq.wrapping_add(2), SeqCst);
+        old_arcs
+    }
+
+    /// Stores all the Arcs at a single point in time.
+    pub fn store_all<T: AtomicArcTuple>(&self, atomics: T, arcs: T::Arcs) {
+        drop(self.swap_all(atomics, arcs));
+    }
+}
+
+impl Default for SnapshotGroup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for SnapshotGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SnapshotGroup")
+            .field("seq", &self.seq.load(Relaxed))
+            .finish()
+    }
+}
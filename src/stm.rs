@@ -0,0 +1,364 @@
+//! Optimistic transactions over several atomic Arc storages.
+//!
+//! Transactions are validated against the version clock of their [`TransactionGroup`], which is
+//! incremented by every committing transaction of the group. Reads are borrows protected by the
+//! domain slots, and are checked to be consistent with the clock when they are made; a
+//! conflicting transaction is then aborted early, without observing any inconsistent state.
+//! Writes are buffered and applied at commit, with the clock locked, after the read pointers
+//! have been validated.
+//!
+//! Committed writes are stored one after the other, so only transactional reads of the same
+//! group are isolated from a commit in progress: a plain [`load`](AtomicArcPtr::load) of one of
+//! the written storages may observe some writes of a transaction but not the others. Storages
+//! written together must then be read in a transaction of the group to be consistent.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    fmt,
+    mem::ManuallyDrop,
+    sync::atomic::{
+        AtomicUsize,
+        Ordering::{Relaxed, SeqCst},
+    },
+};
+
+use crossbeam_utils::Backoff;
+
+#[allow(unused_imports)]
+use crate::msrv::StrictProvenance;
+use crate::{
+    arc::ArcPtr,
+    atomic::{ArcPtrBorrow, AtomicArcPtr},
+    domain::Domain,
+    write_policy::WritePolicy,
+};
+
+static GLOBAL_GROUP: TransactionGroup = TransactionGroup::new();
+
+/// Runs the transaction in the global [`TransactionGroup`], retrying it until it commits without
+/// conflict, and returns its result.
+///
+/// All the transactions run with this function share the same clock, so they may abort each
+/// other even if they access unrelated storages; see [`TransactionGroup::atomically`]. Only
+/// transactional reads are isolated, a plain load may observe a partially applied commit.
+///
+/// # Examples
+///
+/// ```rust
+/// # use std::{collections::BTreeSet, sync::Arc};
+/// # use hazarc::stm;
+/// # hazarc::domain!(Domain(8));
+/// # type AtomicArc<T> = hazarc::AtomicArc<T, Domain>;
+/// let pending = AtomicArc::<BTreeSet<u64>>::from(BTreeSet::from([42]));
+/// let done = AtomicArc::<BTreeSet<u64>>::default();
+/// stm::atomically(|tx| {
+///     let mut pending_ids = (**tx.read(&pending)?).clone();
+///     let mut done_ids = (**tx.read(&done)?).clone();
+///     pending_ids.remove(&42);
+///     done_ids.insert(42);
+///     tx.write(&pending, Arc::new(pending_ids));
+///     tx.write(&done, Arc::new(done_ids));
+///     Ok(())
+/// });
+/// let (pending, done) = stm::atomically(|tx| Ok((tx.read(&pending)?, tx.read(&done)?)));
+/// assert!(pending.is_empty() && done.contains(&42));
+/// ```
+pub fn atomically<'a, F: FnMut(&mut Transaction<'a>) -> Result<R, Conflict>, R>(f: F) -> R {
+    GLOBAL_GROUP.atomically(f)
+}
+
+/// A version clock shared by the transactions accessing related storages.
+///
+/// Transactions of different groups don't abort each other, so unrelated storages should be
+/// accessed in different groups. A given storage should always be accessed in the same group,
+/// as atomicity is only guaranteed with regard to the transactions of the group.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hazarc::stm::TransactionGroup;
+/// # hazarc::domain!(Domain(8));
+/// # type AtomicArc<T> = hazarc::AtomicArc<T, Domain>;
+/// static ACCOUNTS: TransactionGroup = TransactionGroup::new();
+/// let (x, y) = (AtomicArc::<u64>::from(1), AtomicArc::<u64>::from(0));
+/// ACCOUNTS.atomically(|tx| {
+///     let (vx, vy) = (**tx.read(&x)?, **tx.read(&y)?);
+///     tx.write(&x, (vx - 1).into());
+///     tx.write(&y, (vy + 1).into());
+///     Ok(())
+/// });
+/// assert_eq!((**x.load(), **y.load()), (0, 1));
+/// ```
+pub struct TransactionGroup {
+    // Odd while a transaction is committing
+    clock: AtomicUsize,
+}
+
+impl TransactionGroup {
+    /// Constructs a new transaction group.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            clock: AtomicUsize::new(0),
+        }
+    }
+
+    /// Runs the transaction, retrying it until it commits without conflict, and returns its
+    /// result.
+    ///
+    /// Atomicity is only guaranteed with regard to other transactions of the group, so storages
+    /// accessed in a transaction should only be written with transactions of the same group,
+    /// and read with them to be consistent: writes of a commit are stored one after the other,
+    /// so a plain load may observe some of them but not the others.
+    /// The transaction function may be called several times, and should have no side effect
+    /// other than on the transaction.
+    pub fn atomically<'a, F: FnMut(&mut Transaction<'a>) -> Result<R, Conflict>, R>(
+        &'a self,
+        mut f: F,
+    ) -> R {
+        let backoff = Backoff::new();
+        loop {
+            let mut tx = Transaction::new(&self.clock);
+            if let Ok(res) = f(&mut tx) {
+                if tx.commit().is_ok() {
+                    return res;
+                }
+            }
+            backoff.snooze();
+        }
+    }
+}
+
+impl Default for TransactionGroup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for TransactionGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransactionGroup")
+            .field("clock", &self.clock.load(Relaxed))
+            .finish()
+    }
+}
+
+/// Error returned by [`Transaction`] operations when a concurrent transaction has committed.
+///
+/// It should be propagated out of the transaction function, which is then retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conflict;
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "transaction conflict")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Conflict {}
+
+trait TxRead {
+    fn load_ptr(&self) -> *mut ();
+}
+
+impl<A: ArcPtr, D: Domain, W: WritePolicy> TxRead for AtomicArcPtr<A, D, W> {
+    fn load_ptr(&self) -> *mut () {
+        self.load_ptr(SeqCst)
+    }
+}
+
+trait TxWrite {
+    fn addr(&self) -> usize;
+    fn new_ptr(&self) -> *mut ();
+    fn commit(&mut self);
+}
+
+struct Write<'a, A: ArcPtr, D: Domain, W: WritePolicy> {
+    atomic_arc: &'a AtomicArcPtr<A, D, W>,
+    new: Option<A>,
+    // kept until the clock is unlocked, so its drop cannot block other transactions
+    old: Option<A>,
+}
+
+impl<A: ArcPtr, D: Domain, W: WritePolicy> TxWrite for Write<'_, A, D, W> {
+    fn addr(&self) -> usize {
+        self.atomic_arc.addr()
+    }
+    fn new_ptr(&self) -> *mut () {
+        A::as_ptr(self.new.as_ref().unwrap())
+    }
+    fn commit(&mut self) {
+        self.old = Some(self.atomic_arc.swap(self.new.take().unwrap()));
+    }
+}
+
+/// A transaction, passed to the function run by [`atomically`].
+pub struct Transaction<'a> {
+    group_clock: &'a AtomicUsize,
+    clock: usize,
+    reads: Vec<(&'a dyn TxRead, *mut ())>,
+    writes: Vec<Box<dyn TxWrite + 'a>>,
+}
+
+impl<'a> Transaction<'a> {
+    fn new(group_clock: &'a AtomicUsize) -> Self {
+        let backoff = Backoff::new();
+        let mut clock = group_clock.load(SeqCst);
+        while clock & 1 != 0 {
+            backoff.snooze();
+            clock = group_clock.load(SeqCst);
+        }
+        Self {
+            group_clock,
+            clock,
+            reads: Vec::new(),
+            writes: Vec::new(),
+        }
+    }
+
+    fn check(&self) -> Result<(), Conflict> {
+        if self.group_clock.load(SeqCst) != self.clock {
+            return Err(Conflict);
+        }
+        Ok(())
+    }
+
+    /// Reads the Arc stored in the storage, or the Arc written to it by this transaction.
+    ///
+    /// Returns an error if a concurrent transaction has committed since the beginning of this
+    /// one, as the read could then be inconsistent with the previous ones.
+    pub fn read<A: ArcPtr + 'a, D: Domain, W: WritePolicy>(
+        &mut self,
+        atomic_arc: &'a AtomicArcPtr<A, D, W>,
+    ) -> Result<ArcPtrBorrow<A>, Conflict> {
+        if let Some(write) = self.writes.iter().find(|w| w.addr() == atomic_arc.addr()) {
+            // SAFETY: the write was made on the same storage, so with the same pointer type
+            let new = ManuallyDrop::new(unsafe { A::from_ptr(write.new_ptr()) });
+            return Ok(A::clone(&new).into());
+        }
+        let arc = atomic_arc.load();
+        self.check()?;
+        self.reads.push((atomic_arc, A::as_ptr(&arc)));
+        Ok(arc)
+    }
+
+    /// Writes the Arc to the storage when the transaction commits.
+    pub fn write<A: ArcPtr + 'a, D: Domain, W: WritePolicy>(
+        &mut self,
+        atomic_arc: &'a AtomicArcPtr<A, D, W>,
+        new: A,
+    ) {
+        self.writes.retain(|w| w.addr() != atomic_arc.addr());
+        self.writes.push(Box::new(Write {
+            atomic_arc,
+            new: Some(new),
+            old: None,
+        }));
+    }
+
+    fn commit(mut self) -> Result<(), Conflict> {
+        if self.writes.is_empty() {
+            return self.check();
+        }
+        let clock = self.group_clock;
+        // locking the clock fails if another transaction has committed since the beginning
+        if (clock.compare_exchange(self.clock, self.clock + 1, SeqCst, Relaxed)).is_err() {
+            return Err(Conflict);
+        }
+        // storages may have been written outside of transactions
+        if (self.reads.iter()).any(|(atomic_arc, ptr)| atomic_arc.load_ptr() != *ptr) {
+            clock.store(self.clock, SeqCst);
+            return Err(Conflict);
+        }
+        self.writes.iter_mut().for_each(|write| write.commit());
+        clock.store(self.clock.wrapping_add(2), SeqCst);
+        // the replaced Arcs are dropped with the transaction, after the clock is unlocked
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Transaction<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transaction")
+            .field("reads", &self.reads.len())
+            .field("writes", &self.writes.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::sync::Arc;
+
+    use crate::{domain, stm, stm::TransactionGroup, AtomicArc};
+
+    #[test]
+    fn atomically() {
+        domain!(TestDomain(1));
+        let x = AtomicArc::<usize, TestDomain>::from(1);
+        let y = AtomicArc::<usize, TestDomain>::from(0);
+        let mut attempts = 0;
+        stm::atomically(|tx| {
+            attempts += 1;
+            let (vx, vy) = (**tx.read(&x)?, **tx.read(&y)?);
+            if attempts == 1 {
+                stm::atomically(|tx| {
+                    tx.write(&x, 2.into());
+                    Ok(())
+                });
+            }
+            tx.write(&x, 0.into());
+            tx.write(&y, (vx + vy).into());
+            assert_eq!(**tx.read(&y)?, vx + vy);
+            Ok(())
+        });
+        assert_eq!(attempts, 2);
+        assert_eq!((**x.load(), **y.load()), (0, 2));
+    }
+
+    #[test]
+    fn groups() {
+        domain!(TestDomain(1));
+        let group = TransactionGroup::new();
+        let x = AtomicArc::<usize, TestDomain>::from(0);
+        let y = AtomicArc::<usize, TestDomain>::from(0);
+        let mut attempts = 0;
+        group.atomically(|tx| {
+            attempts += 1;
+            let vx = **tx.read(&x)?;
+            // a transaction of another group doesn't conflict
+            stm::atomically(|tx| {
+                tx.write(&y, 1.into());
+                Ok(())
+            });
+            tx.write(&x, (vx + 1).into());
+            Ok(())
+        });
+        assert_eq!(attempts, 1);
+        assert_eq!((**x.load(), **y.load()), (1, 1));
+    }
+
+    #[test]
+    fn drop_after_unlock() {
+        domain!(TestDomain(1));
+        struct Nested<'a>(&'a AtomicArc<usize, TestDomain>);
+        impl Drop for Nested<'_> {
+            fn drop(&mut self) {
+                stm::atomically(|tx| {
+                    let v = **tx.read(self.0)?;
+                    tx.write(self.0, (v + 1).into());
+                    Ok(())
+                });
+            }
+        }
+        let y = AtomicArc::<usize, TestDomain>::from(0);
+        let x = AtomicArc::<Nested, TestDomain>::from(Nested(&y));
+        // the replaced value runs a transaction when dropped, after the clock is unlocked
+        stm::atomically(|tx| {
+            tx.write(&x, Arc::new(Nested(&y)));
+            Ok(())
+        });
+        assert_eq!(**y.load(), 1);
+    }
+}
@@ -0,0 +1,318 @@
+//! Atomic storage for `Arc`-like pointers tagged with spare low bits.
+
+use alloc::sync::Arc;
+use core::{
+    fmt,
+    sync::atomic::Ordering::{Acquire, Relaxed, SeqCst},
+};
+
+#[allow(unused_imports)]
+use crate::msrv::StrictProvenance;
+use crate::{
+    arc::{ArcPtr, ArcRef},
+    atomic::{ArcPtrBorrow, AtomicArcPtr, CachedOrReloaded, TAG_MASK},
+    cache::AtomicArcRef,
+    domain::Domain,
+    write_policy::{Tagged, WritePolicy},
+};
+
+/// An atomic storage for generic `Arc`-like pointers, tagged with `BITS` spare low bits.
+///
+/// [`ArcPtr`] pointers are aligned to 4, so up to 2 bits can be packed next to the pointer,
+/// and both can be updated atomically. The tag can also be updated without touching the Arc.
+///
+/// # Examples
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// # hazarc::domain!(Domain(8));
+/// # type TaggedAtomicArc<T, const BITS: u32> = hazarc::TaggedAtomicArc<T, BITS, Domain>;
+/// const DRAINING: usize = 0b01;
+/// const READ_ONLY: usize = 0b10;
+/// let config = TaggedAtomicArc::<usize, 2>::new(Arc::new(0), 0);
+/// config.set_tag(DRAINING);
+/// let (cfg, tag) = config.load_with_tag();
+/// assert_eq!((**cfg, tag), (0, DRAINING));
+/// assert_eq!(
+///     config.compare_exchange_tag(DRAINING, READ_ONLY),
+///     Ok(DRAINING)
+/// );
+/// ```
+#[repr(transparent)]
+pub struct TaggedAtomicArcPtr<A: ArcPtr, D: Domain, W: WritePolicy, const BITS: u32>(
+    // the write policy makes the inner storage strip the tag of the loaded pointers
+    AtomicArcPtr<A, D, Tagged<W>>,
+);
+
+#[inline(always)]
+#[allow(unstable_name_collisions)]
+fn untagged(ptr: *mut ()) -> *mut () {
+    ptr.map_addr(|addr| addr & !TAG_MASK)
+}
+
+impl<A: ArcPtr, D: Domain, W: WritePolicy, const BITS: u32> TaggedAtomicArcPtr<A, D, W, BITS> {
+    const MASK: usize = {
+        assert!(
+            BITS <= TAG_MASK.count_ones(),
+            "at most 2 tag bits are supported"
+        );
+        (1 << BITS) - 1
+    };
+
+    #[inline(always)]
+    fn check_tag(tag: usize) {
+        assert_eq!(tag & !Self::MASK, 0, "tag overflows {BITS} bits");
+    }
+
+    #[inline(always)]
+    #[allow(unstable_name_collisions)]
+    fn tagged(ptr: *mut (), tag: usize) -> *mut () {
+        Self::check_tag(tag);
+        ptr.map_addr(|addr| addr | tag)
+    }
+
+    #[inline(always)]
+    #[allow(unstable_name_collisions)]
+    fn tag_of(ptr: *mut ()) -> usize {
+        ptr.addr() & Self::MASK
+    }
+
+    /// Constructs a new tagged Arc atomic storage.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tag doesn't fit in `BITS` bits.
+    #[inline]
+    pub fn new(arc: A, tag: usize) -> Self {
+        let this = Self(AtomicArcPtr::new(arc));
+        let ptr = this.0.ptr.load(Relaxed);
+        this.0.ptr.store(Self::tagged(ptr, tag), Relaxed);
+        this
+    }
+
+    /// Loads a borrowed Arc, ignoring the tag.
+    #[inline]
+    pub fn load(&self) -> ArcPtrBorrow<A> {
+        self.0.load()
+    }
+
+    /// Loads a borrowed Arc together with the tag.
+    ///
+    /// The Arc and the tag are loaded atomically.
+    #[inline]
+    pub fn load_with_tag(&self) -> (ArcPtrBorrow<A>, usize) {
+        let mut ptr = self.0.ptr.load(SeqCst);
+        loop {
+            let arc = self.0.load_impl(untagged(ptr));
+            // The tag must be reloaded with the arc if the pointer has been updated meanwhile
+            let ptr_checked = self.0.ptr.load(SeqCst);
+            if untagged(ptr_checked) == A::as_ptr(&arc) {
+                return (arc, Self::tag_of(ptr_checked));
+            }
+            ptr = ptr_checked;
+        }
+    }
+
+    /// Loads an owned Arc, ignoring the tag.
+    #[inline]
+    pub fn load_owned(&self) -> A {
+        self.0.load_owned()
+    }
+
+    /// Loads the tag.
+    #[inline]
+    pub fn tag(&self) -> usize {
+        Self::tag_of(self.0.ptr.load(SeqCst))
+    }
+
+    /// Stores the Arc and the tag, returning the previous ones.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tag doesn't fit in `BITS` bits.
+    pub fn swap_with_tag(&self, arc: A, tag: usize) -> (A, usize) {
+        Self::check_tag(tag);
+        // store a clone to keep an owned arc, see `AtomicArcPtr::swap`
+        let new_ptr = Self::tagged(A::into_ptr(arc.clone()), tag);
+        let old_ptr = self.0.ptr.swap(new_ptr, SeqCst);
+        let old_arc = self.0.swap_impl(untagged(old_ptr), Some(arc));
+        (old_arc, Self::tag_of(old_ptr))
+    }
+
+    /// Stores the Arc and the tag, dropping the previous Arc.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tag doesn't fit in `BITS` bits.
+    pub fn store_with_tag(&self, arc: A, tag: usize) {
+        drop(self.swap_with_tag(arc, tag));
+    }
+
+    /// Sets the tag without modifying the Arc, returning the previous tag.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tag doesn't fit in `BITS` bits.
+    #[allow(unstable_name_collisions)]
+    pub fn set_tag(&self, tag: usize) -> usize {
+        Self::check_tag(tag);
+        let mut ptr = self.0.ptr.load(Relaxed);
+        loop {
+            let new_ptr = ptr.map_addr(|addr| addr & !Self::MASK | tag);
+            match (self.0.ptr).compare_exchange_weak(ptr, new_ptr, SeqCst, Relaxed) {
+                Ok(_) => return Self::tag_of(ptr),
+                Err(p) => ptr = p,
+            }
+        }
+    }
+
+    /// Sets the tag without modifying the Arc if the current tag matches the argument.
+    ///
+    /// Returns the previous tag if the update succeeds, or the current tag otherwise.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new tag doesn't fit in `BITS` bits.
+    #[allow(unstable_name_collisions)]
+    pub fn compare_exchange_tag(&self, current: usize, new: usize) -> Result<usize, usize> {
+        Self::check_tag(new);
+        let mut ptr = self.0.ptr.load(SeqCst);
+        while Self::tag_of(ptr) == current {
+            let new_ptr = ptr.map_addr(|addr| addr & !Self::MASK | new);
+            match (self.0.ptr).compare_exchange_weak(ptr, new_ptr, SeqCst, SeqCst) {
+                Ok(_) => return Ok(current),
+                Err(p) => ptr = p,
+            }
+        }
+        Err(Self::tag_of(ptr))
+    }
+
+    /// Consumes the atomic storage and returns the stored Arc and tag.
+    #[inline]
+    pub fn into_owned(mut self) -> (A, usize) {
+        let tag = Self::tag_of(*self.0.ptr.get_mut());
+        (self.0.into_owned(), tag)
+    }
+
+    /// Stores the new Arc and tag if the current ones match the arguments.
+    ///
+    /// Returns the previous Arc and tag if store succeeds, or loads the current ones otherwise.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new tag doesn't fit in `BITS` bits.
+    pub fn compare_exchange<C: ArcRef<A>>(
+        &self,
+        current: C,
+        current_tag: usize,
+        new: A,
+        new_tag: usize,
+    ) -> Result<(A, usize), (ArcPtrBorrow<A>, usize)> {
+        Self::check_tag(new_tag);
+        let current_ptr = Self::tagged(C::as_ptr(current), current_tag);
+        // store a clone to keep an owned arc, see `AtomicArcPtr::swap`
+        let new_clone = Self::tagged(A::into_ptr(new.clone()), new_tag);
+        match (self.0.ptr).compare_exchange(current_ptr, new_clone, SeqCst, Acquire) {
+            Ok(old_ptr) => Ok((self.0.swap_impl(untagged(old_ptr), Some(new)), current_tag)),
+            Err(_) => {
+                unsafe { A::decr_rc(untagged(new_clone)) };
+                Err(self.load_with_tag())
+            }
+        }
+    }
+}
+
+impl<A: ArcPtr + Default, D: Domain, W: WritePolicy, const BITS: u32> Default
+    for TaggedAtomicArcPtr<A, D, W, BITS>
+{
+    fn default() -> Self {
+        Self::new(A::default(), 0)
+    }
+}
+
+impl<A: ArcPtr + fmt::Debug, D: Domain, W: WritePolicy, const BITS: u32> fmt::Debug
+    for TaggedAtomicArcPtr<A, D, W, BITS>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (arc, tag) = self.load_with_tag();
+        f.debug_struct("TaggedAtomicArcPtr")
+            .field("arc", &*arc)
+            .field("tag", &tag)
+            .finish()
+    }
+}
+
+impl<T, D: Domain, W: WritePolicy, const BITS: u32> From<T>
+    for TaggedAtomicArcPtr<Arc<T>, D, W, BITS>
+{
+    fn from(value: T) -> Self {
+        Self::new(Arc::new(value), 0)
+    }
+}
+
+impl<A: ArcPtr, D: Domain, W: WritePolicy, const BITS: u32> AtomicArcRef
+    for TaggedAtomicArcPtr<A, D, W, BITS>
+{
+    type Arc = A;
+    type Owned = A;
+    type LoadCached<'a>
+        = &'a A
+    where
+        Self::Arc: 'a;
+    type LoadCachedOrReload<'a>
+        = CachedOrReloaded<'a, A>
+    where
+        Self::Arc: 'a;
+    #[inline]
+    fn load_owned(&self) -> Self::Owned {
+        self.0.load_owned()
+    }
+    #[inline(always)]
+    fn load_cached<'a>(&self, cached: &'a mut Self::Owned) -> Self::LoadCached<'a> {
+        self.0.load_cached(cached)
+    }
+    #[inline(always)]
+    fn load_cached_or_reload<'a>(&self, cached: &'a Self::Owned) -> Self::LoadCachedOrReload<'a> {
+        self.0.load_cached_or_reload(cached)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::sync::Arc;
+
+    use crate::{domain, Cache, TaggedAtomicArc};
+
+    #[test]
+    fn tag() {
+        domain!(TestDomain(1));
+        let atomic_arc = TaggedAtomicArc::<usize, 2, TestDomain>::new(Arc::new(0), 1);
+        let (arc, tag) = atomic_arc.load_with_tag();
+        assert_eq!((**arc, tag), (0, 1));
+        assert_eq!(atomic_arc.set_tag(2), 1);
+        assert_eq!(**atomic_arc.load(), 0);
+        assert_eq!(atomic_arc.compare_exchange_tag(1, 3), Err(2));
+        assert_eq!(atomic_arc.compare_exchange_tag(2, 3), Ok(2));
+        let (old, old_tag) = atomic_arc.swap_with_tag(Arc::new(1), 0);
+        assert_eq!((*old, old_tag), (0, 3));
+        assert_eq!(**arc, 0);
+        assert!(atomic_arc
+            .compare_exchange(&old, 0, Arc::new(2), 1)
+            .is_err());
+        let (current, tag) = atomic_arc.load_with_tag();
+        let (prev, _) = atomic_arc
+            .compare_exchange(&current, tag, Arc::new(2), 1)
+            .unwrap();
+        assert_eq!(*prev, 1);
+        let mut cache = Cache::new(Arc::new(atomic_arc));
+        assert_eq!(**cache.load(), 2);
+        assert_eq!(cache.inner().tag(), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn tag_overflow() {
+        domain!(TestDomain(1));
+        TaggedAtomicArc::<usize, 1, TestDomain>::new(Arc::new(0), 2);
+    }
+}
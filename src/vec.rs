@@ -0,0 +1,337 @@
+//! Growable collection of atomic Arc storages.
+//!
+//! Elements are stored in their own shared cell, so the backing array can be grown by publishing
+//! a new one through an internal [`AtomicArc`], while concurrent writes to the elements of the
+//! previous array stay visible.
+
+use alloc::{sync::Arc, vec::Vec};
+use core::{fmt, iter, ops::Deref};
+
+use crate::{
+    arc::ArcRef,
+    batch,
+    domain::Domain,
+    write_policy::{Concurrent, WritePolicy},
+    ArcBorrow, AtomicArc,
+};
+
+type Cells<T, D, W> = Vec<Arc<AtomicArc<T, D, W>>>;
+
+/// A growable array of atomic Arc storages.
+///
+/// Elements can be loaded and written individually, like a `Vec<AtomicArc<T>>`, but the array
+/// can also be grown concurrently with [`push`](Self::push) and [`extend`](Self::extend). It
+/// cannot shrink, so an index once valid stays valid.
+///
+/// Bulk writes with [`store_all`](Self::store_all) and the final drop of the array make a single
+/// pass over the domain nodes for all the elements.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hazarc::vec::AtomicArcVec;
+/// # hazarc::domain!(Domain(8));
+/// let shards = AtomicArcVec::<_, Domain>::from_iter([0, 1].map(Into::into));
+/// assert_eq!(shards.push(2.into()), 2);
+/// shards.store(0, 42.into());
+/// shards.store_all([(1, 43.into()), (2, 44.into())]);
+/// let values: Vec<usize> = shards.load_all().iter().map(|arc| ***arc).collect();
+/// assert_eq!(values, [42, 43, 44]);
+/// ```
+#[cfg(feature = "default-domain")]
+pub struct AtomicArcVec<
+    T,
+    D: Domain = crate::DefaultDomain,
+    W: WritePolicy = crate::write_policy::Concurrent,
+> {
+    cells: AtomicArc<Cells<T, D, W>, D, Concurrent>,
+}
+/// A growable array of atomic Arc storages.
+///
+/// Elements can be loaded and written individually, like a `Vec<AtomicArc<T>>`, but the array
+/// can also be grown concurrently with [`push`](Self::push) and [`extend`](Self::extend). It
+/// cannot shrink, so an index once valid stays valid.
+///
+/// Bulk writes with [`store_all`](Self::store_all) and the final drop of the array make a single
+/// pass over the domain nodes for all the elements.
+#[cfg(not(feature = "default-domain"))]
+pub struct AtomicArcVec<T, D: Domain, W: WritePolicy = crate::write_policy::Concurrent> {
+    cells: AtomicArc<Cells<T, D, W>, D, Concurrent>,
+}
+
+impl<T, D: Domain, W: WritePolicy> AtomicArcVec<T, D, W> {
+    /// Constructs a new empty array.
+    pub fn new() -> Self {
+        Self {
+            cells: AtomicArc::from(Vec::new()),
+        }
+    }
+
+    /// Returns the number of elements.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.cells.load().len()
+    }
+
+    /// Returns `true` if the array contains no element.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Loads a borrowed Arc of the element at index `i`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    #[inline]
+    pub fn load(&self, i: usize) -> ArcBorrow<T> {
+        self.cells.load()[i].load()
+    }
+
+    /// Loads an owned Arc of the element at index `i`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    #[inline]
+    pub fn load_owned(&self, i: usize) -> Arc<T> {
+        self.cells.load()[i].load_owned()
+    }
+
+    /// Loads borrowed Arcs of all the elements.
+    ///
+    /// Elements are loaded one after the other, so concurrent writes may be observed for some of
+    /// them only.
+    pub fn load_all(&self) -> Vec<ArcBorrow<T>> {
+        self.cells.load().iter().map(|cell| cell.load()).collect()
+    }
+
+    /// Stores the Arc at index `i`, and returns the previous one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn swap(&self, i: usize, arc: Arc<T>) -> Arc<T> {
+        self.cells.load()[i].swap(arc)
+    }
+
+    /// Stores the Arc at index `i`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn store(&self, i: usize, arc: Arc<T>) {
+        drop(self.swap(i, arc));
+    }
+
+    /// Stores the new Arc at index `i` if the current one matches the argument.
+    ///
+    /// See [`AtomicArc::compare_exchange`](crate::atomic::AtomicArcPtr::compare_exchange).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn compare_exchange<C: ArcRef<Arc<T>>>(
+        &self,
+        i: usize,
+        current: C,
+        new: Arc<T>,
+    ) -> Result<Arc<T>, ArcBorrow<T>> {
+        self.cells.load()[i].compare_exchange(current, new)
+    }
+
+    /// Stores the Arcs at their associated index, with a single pass over the domain nodes.
+    ///
+    /// See [`batch`](crate::batch()).
+    ///
+    /// # Panics
+    ///
+    /// Panics if an index is out of bounds; no Arc is then stored.
+    pub fn store_all(&self, arcs: impl IntoIterator<Item = (usize, Arc<T>)>) {
+        let cells = self.cells.load();
+        let mut batch = batch::<D>();
+        for (i, arc) in arcs {
+            batch = batch.store(&*cells[i], arc);
+        }
+        batch.commit();
+    }
+
+    /// Appends the Arc to the array, and returns its index.
+    pub fn push(&self, arc: Arc<T>) -> usize {
+        self.extend_impl(iter::once(arc))
+    }
+
+    /// Appends the Arcs to the array.
+    pub fn extend(&self, arcs: impl IntoIterator<Item = Arc<T>>) {
+        self.extend_impl(arcs);
+    }
+
+    fn extend_impl(&self, arcs: impl IntoIterator<Item = Arc<T>>) -> usize {
+        let new_cells: Vec<_> = (arcs.into_iter())
+            .map(|arc| Arc::new(AtomicArc::new(arc)))
+            .collect();
+        let mut current = self.cells.load();
+        loop {
+            let cells = (current.iter().chain(&new_cells)).cloned().collect();
+            match self.cells.compare_exchange(&*current, Arc::new(cells)) {
+                Ok(_) => return current.len(),
+                Err(cells) => current = cells,
+            }
+        }
+    }
+}
+
+impl<T, D: Domain, W: WritePolicy> Default for AtomicArcVec<T, D, W> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, D: Domain, W: WritePolicy> FromIterator<Arc<T>> for AtomicArcVec<T, D, W> {
+    fn from_iter<I: IntoIterator<Item = Arc<T>>>(iter: I) -> Self {
+        let cells = iter
+            .into_iter()
+            .map(|arc| Arc::new(AtomicArc::new(arc)))
+            .collect();
+        Self {
+            cells: AtomicArc::from(Arc::new(cells)),
+        }
+    }
+}
+
+impl<T, D: Domain, W: WritePolicy> Drop for AtomicArcVec<T, D, W> {
+    fn drop(&mut self) {
+        let cells = self.cells.swap(Arc::new(Vec::new()));
+        let Ok(cells) = Arc::try_unwrap(cells) else {
+            return;
+        };
+        // cells shared with a cache still alive are dropped with a pass of their own
+        let cells = cells
+            .into_iter()
+            .filter_map(|cell| Arc::try_unwrap(cell).ok());
+        AtomicArc::drop_all(cells);
+    }
+}
+
+impl<T: fmt::Debug, D: Domain, W: WritePolicy> fmt::Debug for AtomicArcVec<T, D, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let values = self.load_all();
+        f.debug_list()
+            .entries(values.iter().map(|arc| &***arc))
+            .finish()
+    }
+}
+
+/// A cache for a shared [`AtomicArcVec`], caching the Arcs of all its elements.
+///
+/// See [`Cache`](crate::Cache).
+///
+/// # Examples
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// # use hazarc::vec::{AtomicArcVec, VecCache};
+/// # hazarc::domain!(Domain(8));
+/// let shards = Arc::new(AtomicArcVec::<_, Domain>::from_iter([0, 1].map(Into::into)));
+/// let mut cache = VecCache::new(shards.clone());
+/// assert_eq!(**cache.load(1), 1);
+/// shards.push(2.into());
+/// assert_eq!(**cache.load(2), 2);
+/// ```
+pub struct VecCache<V, T, D: Domain, W: WritePolicy> {
+    // cells are declared first to be dropped before the array
+    cells: Arc<Cells<T, D, W>>,
+    cached: Vec<Arc<T>>,
+    vec: V,
+}
+
+impl<V: Deref<Target = AtomicArcVec<T, D, W>>, T, D: Domain, W: WritePolicy> VecCache<V, T, D, W> {
+    /// Constructs a new cache of the array.
+    pub fn new(vec: V) -> Self {
+        let cells = vec.cells.load_owned();
+        let cached = cells.iter().map(|cell| cell.load_owned()).collect();
+        Self { cells, cached, vec }
+    }
+
+    /// Returns a reference to the wrapped array.
+    #[inline]
+    pub fn inner(&self) -> &V {
+        &self.vec
+    }
+
+    /// Consumes the cache and returns the wrapped array.
+    #[inline]
+    pub fn into_inner(self) -> V {
+        self.vec
+    }
+
+    fn reload_cells(&mut self) {
+        let cells = self.vec.cells.load_cached(&mut self.cells);
+        let new_cells = &cells[self.cached.len()..];
+        (self.cached).extend(new_cells.iter().map(|cell| cell.load_owned()));
+    }
+
+    /// Returns a reference to the cached Arc of the element at index `i`, updating it when it is
+    /// outdated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    #[inline]
+    pub fn load(&mut self, i: usize) -> &Arc<T> {
+        if i >= self.cached.len() {
+            self.reload_cells();
+        }
+        self.cells[i].load_cached(&mut self.cached[i])
+    }
+
+    /// Returns the cached Arcs of all the elements, updating the outdated ones.
+    pub fn load_all(&mut self) -> &[Arc<T>] {
+        self.reload_cells();
+        for (cell, cached) in self.cells.iter().zip(&mut self.cached) {
+            cell.load_cached(cached);
+        }
+        &self.cached
+    }
+}
+
+impl<V: fmt::Debug, T: fmt::Debug, D: Domain, W: WritePolicy> fmt::Debug for VecCache<V, T, D, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VecCache")
+            .field("vec", &self.vec)
+            .field("cached", &self.cached)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{sync::Arc, vec::Vec};
+
+    use crate::{
+        domain,
+        vec::{AtomicArcVec, VecCache},
+        write_policy::Concurrent,
+    };
+
+    #[test]
+    fn vec() {
+        domain!(TestDomain(1));
+        let vec = AtomicArcVec::<usize, TestDomain, Concurrent>::from_iter([Arc::new(0)]);
+        let mut cache = VecCache::new(&vec);
+        let zero = vec.load_owned(0);
+        assert_eq!(vec.push(1.into()), 1);
+        vec.extend([2, 3].map(Into::into));
+        assert!(vec.compare_exchange(0, &zero, 4.into()).is_ok());
+        assert_eq!(**vec.compare_exchange(0, &zero, 5.into()).unwrap_err(), 4);
+        assert_eq!(**cache.load(3), 3);
+        vec.store_all([(1, 5.into()), (3, 6.into())]);
+        let values = |arcs: &[Arc<usize>]| arcs.iter().map(|arc| **arc).collect::<Vec<_>>();
+        assert_eq!(values(cache.load_all()), [4, 5, 2, 6]);
+        let borrow = vec.load(2);
+        drop(cache);
+        drop(vec);
+        assert_eq!(**borrow, 2);
+    }
+}
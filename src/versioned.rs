@@ -0,0 +1,234 @@
+//! Atomic Arc storage with monotonic version numbers.
+//!
+//! The version is stored alongside the value in the Arc, so a load returns both consistently,
+//! without any additional synchronization on the read path. Writes compute the new version from
+//! the current Arc and store it with a compare-and-swap, retrying on concurrent writes.
+
+use alloc::sync::Arc;
+use core::{fmt, ops::Deref, sync::atomic::Ordering::SeqCst};
+
+use crate::{
+    arc::ArcPtr, atomic::ArcPtrBorrow, domain::Domain, write_policy::WritePolicy, ArcBorrow,
+    AtomicArc,
+};
+
+/// A value with its version number.
+#[derive(Clone)]
+pub struct Versioned<T> {
+    version: u64,
+    value: T,
+}
+
+impl<T> Versioned<T> {
+    /// The version is set when the value is stored.
+    pub(crate) fn new(value: T) -> Self {
+        Self { version: 0, value }
+    }
+
+    /// Returns the version number.
+    #[inline]
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Sets the version following the one of the current value, before storing it.
+    #[inline]
+    pub(crate) fn set_next_version<U>(&mut self, current: &Versioned<U>) {
+        self.version = current.version + 1;
+    }
+}
+
+impl<T> Deref for Versioned<T> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Versioned<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Versioned")
+            .field("version", &self.version)
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
+/// An [`AtomicArc`] whose stored values are numbered.
+///
+/// The initial value has version 0, and every successful write increments the version by one.
+/// Loaded values can then be correlated with writes.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hazarc::versioned::VersionedAtomicArc;
+/// # hazarc::domain!(Domain(8));
+/// let atomic_arc = VersionedAtomicArc::<_, Domain>::new("foo");
+/// let borrow = atomic_arc.load();
+/// assert_eq!(borrow.version(), 0);
+/// atomic_arc.store("bar");
+/// assert!(!borrow.is_latest(&atomic_arc));
+/// let borrow = atomic_arc.load_if_newer(borrow.version()).unwrap();
+/// assert_eq!((borrow.version(), ***borrow), (1, "bar"));
+/// ```
+#[cfg(feature = "default-domain")]
+pub struct VersionedAtomicArc<
+    T,
+    D: Domain = crate::DefaultDomain,
+    W: WritePolicy = crate::write_policy::Concurrent,
+>(AtomicArc<Versioned<T>, D, W>);
+/// An [`AtomicArc`] whose stored values are numbered.
+///
+/// The initial value has version 0, and every successful write increments the version by one.
+/// Loaded values can then be correlated with writes.
+#[cfg(not(feature = "default-domain"))]
+pub struct VersionedAtomicArc<T, D: Domain, W: WritePolicy = crate::write_policy::Concurrent>(
+    AtomicArc<Versioned<T>, D, W>,
+);
+
+impl<T, D: Domain, W: WritePolicy> VersionedAtomicArc<T, D, W> {
+    /// Constructs a new Arc atomic storage, with version 0.
+    pub fn new(value: T) -> Self {
+        Self(AtomicArc::from(Versioned::new(value)))
+    }
+
+    /// Returns the inner atomic storage.
+    ///
+    /// Writing directly to it doesn't increment the version.
+    #[inline]
+    pub fn inner(&self) -> &AtomicArc<Versioned<T>, D, W> {
+        &self.0
+    }
+
+    /// Loads a borrowed Arc, with its version.
+    #[inline]
+    pub fn load(&self) -> ArcBorrow<Versioned<T>> {
+        self.0.load()
+    }
+
+    /// Loads an owned Arc, with its version.
+    #[inline]
+    pub fn load_owned(&self) -> Arc<Versioned<T>> {
+        self.0.load_owned()
+    }
+
+    /// Loads the current Arc if its version is greater than the given one.
+    #[inline]
+    pub fn load_if_newer(&self, version: u64) -> Option<ArcBorrow<Versioned<T>>> {
+        Some(self.load()).filter(|arc| arc.version > version)
+    }
+
+    /// Returns the current version.
+    #[inline]
+    pub fn version(&self) -> u64 {
+        self.load().version
+    }
+
+    /// Stores the value with the next version, and returns the previous Arc.
+    pub fn swap(&self, value: T) -> Arc<Versioned<T>> {
+        self.swap_impl(&mut Arc::new(Versioned::new(value)))
+    }
+
+    /// Stores the new Arc with the next version, and returns the previous one.
+    pub(crate) fn swap_impl(&self, new: &mut Arc<Versioned<T>>) -> Arc<Versioned<T>> {
+        let mut current = self.load();
+        loop {
+            match self.compare_exchange_impl(&current, new) {
+                Ok(old_arc) => return old_arc,
+                Err(arc) => current = arc,
+            }
+        }
+    }
+
+    /// Stores the value with the next version.
+    pub fn store(&self, value: T) {
+        drop(self.swap(value));
+    }
+
+    /// Stores the value with the next version if the current Arc matches the argument.
+    ///
+    /// Returns the previous Arc if store succeeds, or loads the current Arc otherwise.
+    pub fn compare_exchange(
+        &self,
+        current: &Arc<Versioned<T>>,
+        value: T,
+    ) -> Result<Arc<Versioned<T>>, ArcBorrow<Versioned<T>>> {
+        self.compare_exchange_impl(current, &mut Arc::new(Versioned::new(value)))
+    }
+
+    /// Stores the new Arc with the next version if the current Arc matches the argument.
+    ///
+    /// The new Arc must not have been shared.
+    pub(crate) fn compare_exchange_impl(
+        &self,
+        current: &Arc<Versioned<T>>,
+        new: &mut Arc<Versioned<T>>,
+    ) -> Result<Arc<Versioned<T>>, ArcBorrow<Versioned<T>>> {
+        // the new Arc is only shared once stored, so it is still unique on failure
+        Arc::get_mut(new).unwrap().set_next_version(current);
+        self.0.compare_exchange(current, new.clone())
+    }
+
+    /// Fetches the current value, applies a function on it and tries to store the result with
+    /// the next version if the current Arc has not changed.
+    ///
+    /// Returns `Err` with the current Arc if the function returns `None`.
+    pub fn fetch_update<F: FnMut(&T) -> Option<T>>(
+        &self,
+        mut f: F,
+    ) -> Result<Arc<Versioned<T>>, ArcBorrow<Versioned<T>>> {
+        let mut current = self.load();
+        while let Some(value) = f(&current.value) {
+            match self.compare_exchange(&current, value) {
+                Ok(old_arc) => return Ok(old_arc),
+                Err(arc) => current = arc,
+            }
+        }
+        Err(current)
+    }
+}
+
+impl<T: fmt::Debug, D: Domain, W: WritePolicy> fmt::Debug for VersionedAtomicArc<T, D, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("VersionedAtomicArc")
+            .field(&**self.load())
+            .finish()
+    }
+}
+
+impl<T> ArcPtrBorrow<Arc<Versioned<T>>> {
+    /// Returns `true` if the borrowed Arc is still the one stored in the versioned storage.
+    #[inline]
+    pub fn is_latest<D: Domain, W: WritePolicy>(
+        &self,
+        atomic: &VersionedAtomicArc<T, D, W>,
+    ) -> bool {
+        atomic.0.load_ptr(SeqCst) == ArcPtr::as_ptr(&**self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{domain, versioned::VersionedAtomicArc, write_policy::Concurrent};
+
+    #[test]
+    fn versioned() {
+        domain!(TestDomain(1));
+        let atomic_arc = VersionedAtomicArc::<usize, TestDomain, Concurrent>::new(0);
+        let borrow = atomic_arc.load();
+        assert!(borrow.is_latest(&atomic_arc));
+        assert!(atomic_arc.load_if_newer(0).is_none());
+        assert_eq!(atomic_arc.swap(1).version(), 0);
+        assert!(!borrow.is_latest(&atomic_arc));
+        assert!(atomic_arc.compare_exchange(&borrow, 2).is_err());
+        assert_eq!(
+            atomic_arc.fetch_update(|v| Some(v + 1)).unwrap().version(),
+            1
+        );
+        let latest = atomic_arc.load_if_newer(borrow.version()).unwrap();
+        assert_eq!((latest.version(), ***latest), (2, 2));
+        assert_eq!(atomic_arc.version(), 2);
+    }
+}
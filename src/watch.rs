@@ -0,0 +1,246 @@
+//! Single-producer, multi-consumer channel retaining only the last sent value.
+//!
+//! The channel is built on an [`AtomicArc`] with the
+//! [`Serialized`] write policy, as there is a unique [`Writer`]. Each [`Reader`] embeds a
+//! [`Cache`], so reading an up-to-date value is almost free.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # #[cfg(feature = "default-domain")]
+//! # {
+//! let (mut writer, mut reader) = hazarc::watch::channel(0);
+//! assert_eq!(**reader.borrow(), 0);
+//! writer.store(1.into());
+//! assert!(reader.has_changed());
+//! assert_eq!(**reader.borrow(), 1);
+//! assert!(!reader.has_changed());
+//! # }
+//! ```
+
+use alloc::sync::Arc;
+use core::{
+    fmt,
+    future::poll_fn,
+    ops::Deref,
+    sync::atomic::{AtomicBool, Ordering::SeqCst},
+};
+
+use crate::{
+    arc::ArcPtr,
+    domain::Domain,
+    notify::{self, WaiterKey},
+    write_policy::Serialized,
+    ArcBorrow, AtomicArc, Cache,
+};
+
+struct Shared<T, D: Domain> {
+    atomic_arc: AtomicArc<T, D, Serialized>,
+    closed: AtomicBool,
+}
+
+impl<T, D: Domain> Deref for Shared<T, D> {
+    type Target = AtomicArc<T, D, Serialized>;
+    fn deref(&self) -> &Self::Target {
+        &self.atomic_arc
+    }
+}
+
+/// Creates a new watch channel in the default domain, returning the writer and a reader.
+#[cfg(feature = "default-domain")]
+#[cfg_attr(docsrs, doc(cfg(feature = "default-domain")))]
+pub fn channel<T>(initial: T) -> (Writer<T>, Reader<T>) {
+    let writer = Writer::new(initial);
+    let reader = writer.subscribe();
+    (writer, reader)
+}
+
+/// The unique writing half of a watch channel.
+///
+/// The channel is closed when it is dropped.
+#[cfg(feature = "default-domain")]
+pub struct Writer<T, D: Domain = crate::DefaultDomain>(Arc<Shared<T, D>>);
+/// The unique writing half of a watch channel.
+///
+/// The channel is closed when it is dropped.
+#[cfg(not(feature = "default-domain"))]
+pub struct Writer<T, D: Domain>(Arc<Shared<T, D>>);
+
+impl<T, D: Domain> Writer<T, D> {
+    /// Creates a new watch channel with the given initial value.
+    ///
+    /// Readers are created with [`subscribe`](Self::subscribe).
+    pub fn new(initial: T) -> Self {
+        Self(Arc::new(Shared {
+            atomic_arc: AtomicArc::from(initial),
+            closed: AtomicBool::new(false),
+        }))
+    }
+
+    /// Creates a new reader, which sees the current value as already seen.
+    pub fn subscribe(&self) -> Reader<T, D> {
+        Reader(Cache::new(self.0.clone()))
+    }
+
+    /// Returns the number of readers.
+    pub fn reader_count(&self) -> usize {
+        Arc::strong_count(&self.0) - 1
+    }
+
+    /// Loads the current value.
+    pub fn load(&self) -> ArcBorrow<T> {
+        self.0.load()
+    }
+
+    /// Stores a new value and returns the previous one, notifying the readers.
+    pub fn swap(&mut self, arc: Arc<T>) -> Arc<T> {
+        self.0.swap(arc)
+    }
+
+    /// Stores a new value, notifying the readers.
+    pub fn store(&mut self, arc: Arc<T>) {
+        self.0.store(arc);
+    }
+}
+
+impl<T, D: Domain> Drop for Writer<T, D> {
+    fn drop(&mut self) {
+        self.0.closed.store(true, SeqCst);
+        notify::notify(self.0.addr());
+    }
+}
+
+impl<T: fmt::Debug, D: Domain> fmt::Debug for Writer<T, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Writer").field(&**self.load()).finish()
+    }
+}
+
+/// A reading half of a watch channel.
+///
+/// It caches the last seen value, which is updated by [`borrow`](Self::borrow) and
+/// [`changed`](Self::changed).
+#[cfg(feature = "default-domain")]
+pub struct Reader<T, D: Domain = crate::DefaultDomain>(Cache<Arc<Shared<T, D>>>);
+/// A reading half of a watch channel.
+///
+/// It caches the last seen value, which is updated by [`borrow`](Self::borrow) and
+/// [`changed`](Self::changed).
+#[cfg(not(feature = "default-domain"))]
+pub struct Reader<T, D: Domain>(Cache<Arc<Shared<T, D>>>);
+
+impl<T, D: Domain> Reader<T, D> {
+    /// Returns the latest value, marking it as seen.
+    #[inline]
+    pub fn borrow(&mut self) -> &Arc<T> {
+        self.0.load()
+    }
+
+    /// Returns `true` if a new value has been sent since the last seen one.
+    #[inline]
+    pub fn has_changed(&self) -> bool {
+        self.0.inner().load_ptr(SeqCst) != ArcPtr::as_ptr(self.0.cached())
+    }
+
+    /// Returns `true` if the writer has been dropped.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.0.inner().closed.load(SeqCst)
+    }
+
+    /// Waits for a new value, and returns it, marking it as seen.
+    ///
+    /// Returns an error if the writer has been dropped and the latest value has already been
+    /// seen.
+    pub async fn changed(&mut self) -> Result<&Arc<T>, Closed> {
+        let shared = &**self.0.inner();
+        let seen = ArcPtr::as_ptr(self.0.cached());
+        let mut key = WaiterKey::default();
+        poll_fn(|cx| {
+            key.poll_until(shared.addr(), cx.waker(), || {
+                if shared.load_ptr(SeqCst) != seen {
+                    Some(Ok(()))
+                } else if shared.closed.load(SeqCst) {
+                    Some(Err(Closed))
+                } else {
+                    None
+                }
+            })
+        })
+        .await?;
+        Ok(self.borrow())
+    }
+}
+
+impl<T, D: Domain> Clone for Reader<T, D> {
+    fn clone(&self) -> Self {
+        Self(Cache::new(self.0.inner().clone()))
+    }
+}
+
+impl<T: fmt::Debug, D: Domain> fmt::Debug for Reader<T, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Reader").field(&**self.0.cached()).finish()
+    }
+}
+
+/// Error returned by [`Reader::changed`] when the writer has been dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Closed;
+
+impl fmt::Display for Closed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "watch channel closed")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Closed {}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{boxed::Box, sync::Arc};
+    use core::{
+        future::Future,
+        sync::atomic::Ordering::Relaxed,
+        task::{Context, Poll, Waker},
+    };
+
+    use crate::{
+        domain,
+        notify::tests::CountWaker,
+        watch::{Closed, Writer},
+    };
+
+    #[test]
+    fn watch() {
+        domain!(TestDomain(1));
+        let count = Arc::new(CountWaker::default());
+        let waker = Waker::from(count.clone());
+        let mut cx = Context::from_waker(&waker);
+        let mut writer = Writer::<usize, TestDomain>::new(0);
+        let mut reader = writer.subscribe();
+        let mut reader2 = reader.clone();
+        assert_eq!(writer.reader_count(), 2);
+        assert_eq!(**reader.borrow(), 0);
+        assert!(!reader.has_changed());
+        let mut changed = Box::pin(reader.changed());
+        assert!(changed.as_mut().poll(&mut cx).is_pending());
+        writer.store(1.into());
+        assert_eq!(count.0.load(Relaxed), 1);
+        assert!(matches!(changed.as_mut().poll(&mut cx), Poll::Ready(Ok(arc)) if **arc == 1));
+        drop(changed);
+        assert!(reader2.has_changed());
+        assert_eq!(**reader2.borrow(), 1);
+        let mut changed = Box::pin(reader.changed());
+        assert!(changed.as_mut().poll(&mut cx).is_pending());
+        drop(writer);
+        assert_eq!(count.0.load(Relaxed), 2);
+        assert!(matches!(
+            changed.as_mut().poll(&mut cx),
+            Poll::Ready(Err(Closed))
+        ));
+        drop(changed);
+        assert!(reader.is_closed());
+    }
+}
@@ -0,0 +1,246 @@
+//! Atomic storage for `Weak` pointers.
+
+use alloc::sync::{Arc, Weak};
+use core::{fmt, ops::Deref};
+
+use crate::{
+    arc::{ArcPtr, ArcRef, NonNullArcPtr},
+    atomic::{ArcPtrBorrow, AtomicOptionArcPtr},
+    domain::Domain,
+    write_policy::WritePolicy,
+    ArcBorrow,
+};
+
+/// A [`Weak`] pointer downgraded from an [`Arc`], stored in [`AtomicWeak`].
+///
+/// A dangling `Weak`, i.e. constructed with [`Weak::new`], cannot be stored in an atomic
+/// storage, as its pointer is not aligned, so this wrapper is only constructed with
+/// [`Arc::downgrade`].
+pub struct DowngradedWeak<T>(Weak<T>);
+
+impl<T> DowngradedWeak<T> {
+    /// Constructs a new `DowngradedWeak` from the given Arc.
+    #[inline]
+    pub fn new(arc: &Arc<T>) -> Self {
+        Self(Arc::downgrade(arc))
+    }
+
+    /// Returns the inner `Weak`.
+    #[inline]
+    pub fn into_inner(self) -> Weak<T> {
+        self.0
+    }
+}
+
+impl<T> Clone for DowngradedWeak<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Deref for DowngradedWeak<T> {
+    type Target = Weak<T>;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> From<&Arc<T>> for DowngradedWeak<T> {
+    fn from(value: &Arc<T>) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T> From<DowngradedWeak<T>> for Weak<T> {
+    fn from(value: DowngradedWeak<T>) -> Self {
+        value.0
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for DowngradedWeak<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(not(target_pointer_width = "16"))]
+unsafe impl<T> ArcPtr for DowngradedWeak<T> {
+    #[inline(always)]
+    unsafe fn from_ptr(ptr: *mut ()) -> Self {
+        Self(unsafe { Weak::from_raw(ptr.cast()) })
+    }
+    #[inline(always)]
+    fn into_ptr(arc: Self) -> *mut () {
+        Weak::into_raw(arc.0).cast_mut().cast()
+    }
+    #[inline(always)]
+    fn as_ptr(arc: &Self) -> *mut () {
+        Weak::as_ptr(&arc.0).cast_mut().cast()
+    }
+}
+
+// The pointer of a `Weak` downgraded from an `Arc` is the Arc one, so it is non-null and aligned
+#[cfg(not(target_pointer_width = "16"))]
+unsafe impl<T> NonNullArcPtr for DowngradedWeak<T> {}
+
+impl<T> ArcRef<Option<DowngradedWeak<T>>> for &Arc<T> {
+    fn as_ptr(this: Self) -> *mut () {
+        <Arc<T> as ArcPtr>::as_ptr(this)
+    }
+}
+
+/// An atomic storage for [`Weak`] pointers, upgraded on load.
+///
+/// It allows pointing to an object without keeping it alive, e.g. in an observer registry.
+/// Loaded `Weak` pointers are protected by the domain's borrow slots, like
+/// [`AtomicArc`](crate::AtomicArc) ones.
+///
+/// # Examples
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// # hazarc::domain!(Domain(8));
+/// # type AtomicWeak<T> = hazarc::AtomicWeak<T, Domain>;
+/// let arc = Arc::new(0);
+/// let atomic_weak = AtomicWeak::new(&arc);
+/// assert_eq!(atomic_weak.load_upgrade().as_deref().map(|a| **a), Some(0));
+/// drop(arc);
+/// assert!(atomic_weak.load_upgrade().is_none());
+/// ```
+pub struct AtomicWeak<T, D: Domain, W: WritePolicy>(AtomicOptionArcPtr<DowngradedWeak<T>, D, W>);
+
+impl<T, D: Domain, W: WritePolicy> AtomicWeak<T, D, W> {
+    /// Constructs a new `Weak` atomic storage, downgrading the given Arc.
+    #[inline]
+    pub fn new(arc: &Arc<T>) -> Self {
+        Self(AtomicOptionArcPtr::new(Some(DowngradedWeak::new(arc))))
+    }
+
+    /// Constructs a new `Weak` atomic storage with `None`.
+    #[inline]
+    pub const fn none() -> Self {
+        Self(AtomicOptionArcPtr::none())
+    }
+
+    /// Returns a reference to the inner `AtomicOptionArcPtr`.
+    #[inline]
+    pub fn inner(&self) -> &AtomicOptionArcPtr<DowngradedWeak<T>, D, W> {
+        &self.0
+    }
+
+    /// Returns `true` if the stored `Weak` is `None`.
+    ///
+    /// A stored `Weak` may not be upgradable anymore, even if this method returns `false`.
+    #[inline]
+    pub fn is_none(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// Loads a borrowed `Weak`.
+    #[inline]
+    pub fn load(&self) -> Option<ArcPtrBorrow<DowngradedWeak<T>>> {
+        self.0.load()
+    }
+
+    /// Loads the stored `Weak` and upgrades it.
+    ///
+    /// Returns `None` if there is no stored `Weak`, or if it cannot be upgraded.
+    #[inline]
+    pub fn load_upgrade(&self) -> Option<ArcBorrow<T>> {
+        self.0.load()?.upgrade().map(ArcPtrBorrow::from)
+    }
+
+    /// Stores a downgraded Arc and returns the previous `Weak`.
+    pub fn swap_downgrade(&self, arc: Option<&Arc<T>>) -> Option<Weak<T>> {
+        self.0.swap(arc.map(DowngradedWeak::new)).map(Into::into)
+    }
+
+    /// Stores a downgraded Arc, dropping the previous `Weak`.
+    pub fn store_downgrade(&self, arc: &Arc<T>) {
+        self.0.store(Some(DowngradedWeak::new(arc)));
+    }
+
+    /// Stores `None`, dropping the previous `Weak`.
+    pub fn clear(&self) {
+        self.0.store(None);
+    }
+
+    /// Consumes the atomic storage and returns the stored `Weak`.
+    #[inline]
+    pub fn into_owned(self) -> Option<Weak<T>> {
+        self.0.into_owned().map(Into::into)
+    }
+
+    /// Stores a downgraded Arc if the current `Weak` matches the argument.
+    ///
+    /// The current `Weak` can be compared to an `Arc`, as they point to the same allocation.
+    ///
+    /// Returns the previous `Weak` if store succeeds, or loads the current `Weak` otherwise.
+    pub fn compare_exchange<C: ArcRef<Option<DowngradedWeak<T>>>>(
+        &self,
+        current: C,
+        new: Option<&Arc<T>>,
+    ) -> Result<Option<Weak<T>>, Option<ArcPtrBorrow<DowngradedWeak<T>>>> {
+        (self.0)
+            .compare_exchange(current, new.map(DowngradedWeak::new))
+            .map(|weak| weak.map(Into::into))
+    }
+}
+
+impl<T, D: Domain, W: WritePolicy> Default for AtomicWeak<T, D, W> {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl<T: fmt::Debug, D: Domain, W: WritePolicy> fmt::Debug for AtomicWeak<T, D, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AtomicWeak")
+            .field(&self.load_upgrade().as_deref().map(|arc| &**arc))
+            .finish()
+    }
+}
+
+impl<T, D: Domain, W: WritePolicy> From<&Arc<T>> for AtomicWeak<T, D, W> {
+    fn from(value: &Arc<T>) -> Self {
+        Self::new(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::sync::Arc;
+
+    use crate::{domain, AtomicWeak};
+
+    #[test]
+    fn load_upgrade() {
+        domain!(TestDomain(1));
+        let arc = Arc::new(0);
+        let atomic_weak = AtomicWeak::<usize, TestDomain>::new(&arc);
+        let borrow = atomic_weak.load().unwrap();
+        assert_eq!(**atomic_weak.load_upgrade().unwrap(), 0);
+        let arc2 = Arc::new(1);
+        atomic_weak.store_downgrade(&arc2);
+        assert_eq!(borrow.strong_count(), 1);
+        drop(arc);
+        assert!(borrow.upgrade().is_none());
+        assert_eq!(**atomic_weak.load_upgrade().unwrap(), 1);
+        drop(arc2);
+        assert!(atomic_weak.load_upgrade().is_none());
+        assert!(!atomic_weak.is_none());
+        atomic_weak.clear();
+        assert!(atomic_weak.is_none());
+    }
+
+    #[test]
+    fn compare_exchange() {
+        domain!(TestDomain(1));
+        let (arc1, arc2) = (Arc::new(0), Arc::new(1));
+        let atomic_weak = AtomicWeak::<usize, TestDomain>::new(&arc1);
+        assert!(atomic_weak.compare_exchange(&arc2, Some(&arc2)).is_err());
+        assert!(atomic_weak.compare_exchange(&arc1, Some(&arc2)).is_ok());
+        assert_eq!(**atomic_weak.load_upgrade().unwrap(), 1);
+    }
+}
@@ -1,5 +1,7 @@
 //! Write concurrency model for the `AtomicArc` algorithm.
 
+use core::marker::PhantomData;
+
 /// Generic parameter of [`AtomicArc`](crate::atomic::AtomicArcPtr) which specifies
 /// the assumption about concurrent writes.
 ///
@@ -26,11 +28,20 @@ impl WritePolicy for Concurrent {}
 pub struct Serialized;
 impl WritePolicy for Serialized {}
 
+/// [`WritePolicy`] of the storage wrapped by
+/// [`TaggedAtomicArcPtr`](crate::tagged::TaggedAtomicArcPtr), whose stored pointer is tagged.
+pub(crate) struct Tagged<W>(PhantomData<W>);
+impl<W: WritePolicy> WritePolicy for Tagged<W> {}
+
 mod private {
-    use crate::write_policy::{Concurrent, Serialized};
+    use crate::write_policy::{Concurrent, Serialized, Tagged};
 
     pub trait WritePolicy {
         const CONCURRENT: bool;
+        /// Bits of the stored pointer stripped when it is loaded.
+        ///
+        /// It is only set for tagged storages, so it is a no-op for the others.
+        const TAG_MASK: usize = 0;
     }
     impl WritePolicy for Concurrent {
         const CONCURRENT: bool = true;
@@ -38,4 +49,8 @@ mod private {
     impl WritePolicy for Serialized {
         const CONCURRENT: bool = false;
     }
+    impl<W: super::WritePolicy> WritePolicy for Tagged<W> {
+        const CONCURRENT: bool = W::CONCURRENT;
+        const TAG_MASK: usize = crate::atomic::TAG_MASK;
+    }
 }
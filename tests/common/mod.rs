@@ -1,9 +1,17 @@
 use std::{
-    sync::atomic::{AtomicUsize, Ordering::Relaxed},
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering::Relaxed},
+        Arc,
+    },
+    task::{Context, Wake, Waker},
     thread,
 };
 
-use hazarc::{domain, ArcBorrow, AtomicArc, AtomicOptionArc};
+use hazarc::{
+    domain, snapshot::SnapshotGroup, stm, vec::AtomicArcVec, ArcBorrow, AtomicArc, AtomicOptionArc,
+};
 
 use super::{WritePolicy, SLOTS};
 
@@ -134,3 +142,234 @@ fn seq_cst_ordering() {
         assert!(a.join().unwrap().is_some() || b.join().unwrap().is_some());
     });
 }
+
+#[test]
+fn concurrent_get_or_init() {
+    domain!(TestDomain(SLOTS));
+    let barrier = SpinBarrier::new(2);
+    let atomic_arc = AtomicOptionArc::<usize, TestDomain, WritePolicy>::none();
+    let init = |i| {
+        let atomic_arc = &atomic_arc;
+        barrier.wrap(move || atomic_arc.get_or_init(|| i).into_owned())
+    };
+    let (a, b) = thread::scope(|s| {
+        let a = s.spawn(init(0));
+        let b = s.spawn(init(1));
+        (a.join().unwrap(), b.join().unwrap())
+    });
+    assert!(Arc::ptr_eq(&a, &b));
+    assert!(Arc::ptr_eq(&a, &atomic_arc.load().unwrap()));
+    assert_eq!(
+        atomic_arc
+            .get_or_try_init(|| Err::<usize, _>(()))
+            .as_deref()
+            .map(|a| **a),
+        Ok(*a)
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn wait_changed() {
+    domain!(TestDomain(SLOTS));
+    let barrier = SpinBarrier::new(2);
+    let atomic_arc = AtomicArc::<usize, TestDomain, WritePolicy>::from(0);
+    let seen = atomic_arc.load_owned();
+    thread::scope(|s| {
+        s.spawn(barrier.wrap(|| {
+            atomic_arc.store(1.into());
+            atomic_arc.store(2.into());
+        }));
+        barrier.wait();
+        assert_ne!(**atomic_arc.wait_changed(&seen), 0);
+        assert_eq!(**atomic_arc.wait_until(|arc| **arc == 2), 2);
+    });
+}
+
+#[test]
+fn swap_and_synchronize() {
+    domain!(TestDomain(SLOTS));
+    let barrier = SpinBarrier::new(2);
+    let atomic_arc = AtomicArc::<usize, TestDomain, WritePolicy>::from(0);
+    let borrow = atomic_arc.load();
+    thread::scope(|s| {
+        s.spawn(barrier.wrap(move || drop(borrow)));
+        barrier.wait();
+        assert_eq!(atomic_arc.swap_and_synchronize(1.into()), 0);
+    });
+    struct NoopWaker;
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+    let waker = Waker::from(Arc::new(NoopWaker));
+    let mut cx = Context::from_waker(&waker);
+    let borrow = atomic_arc.load();
+    let mut synchronize = atomic_arc.swap_and_synchronize_async(2.into());
+    assert!(Pin::new(&mut synchronize).poll(&mut cx).is_pending());
+    drop(borrow);
+    assert_eq!(Pin::new(&mut synchronize).poll(&mut cx), 1.into());
+}
+
+#[test]
+fn update_mut_and_edit() {
+    domain!(TestDomain(SLOTS));
+    let atomic_arc = AtomicArc::<Vec<usize>, TestDomain, WritePolicy>::from(vec![0]);
+    assert_eq!(*atomic_arc.update_mut(|v| v.push(1)), [0]);
+    atomic_arc.edit().push(2);
+    assert_eq!(**atomic_arc.load(), [0, 1, 2]);
+    let mut edit = atomic_arc.edit();
+    edit.clear();
+    edit.abort();
+    let mut edit = atomic_arc.edit();
+    edit.pop();
+    assert_eq!(*edit.publish().unwrap(), [0, 1, 2]);
+    assert_eq!(**atomic_arc.load(), [0, 1]);
+}
+
+#[test]
+fn compare_exchange() {
+    domain!(TestDomain(SLOTS));
+    let atomic_arc = AtomicArc::<usize, TestDomain, WritePolicy>::from(0);
+    let current = atomic_arc.load();
+    assert_eq!(*atomic_arc.compare_exchange(&current, 1.into()).unwrap(), 0);
+    assert_eq!(
+        **atomic_arc.compare_exchange(&current, 2.into()).unwrap_err(),
+        1
+    );
+    assert_eq!(*atomic_arc.fetch_update(|i| Some(**i + 1)).unwrap(), 1);
+    assert_eq!(**atomic_arc.load(), 2);
+}
+
+#[test]
+fn store_if_ne() {
+    domain!(TestDomain(SLOTS));
+    let atomic_arc = AtomicArc::<usize, TestDomain, WritePolicy>::from(0);
+    let current = atomic_arc.load_owned();
+    assert!(!atomic_arc.store_if_ne(0.into()));
+    assert!(Arc::ptr_eq(&atomic_arc.load(), &current));
+    assert!(atomic_arc.store_if_ne(1.into()));
+    assert_eq!(
+        **atomic_arc.compare_exchange_eq(&0, 2.into()).unwrap_err(),
+        1
+    );
+    atomic_arc.store(0.into());
+    assert_eq!(*atomic_arc.compare_exchange_eq(&0, 2.into()).unwrap(), 0);
+    assert_eq!(**atomic_arc.load(), 2);
+}
+
+#[test]
+fn snapshot() {
+    domain!(TestDomain(SLOTS));
+    let group = SnapshotGroup::new();
+    let a = AtomicArc::<usize, TestDomain, WritePolicy>::from(0);
+    let b = AtomicArc::<usize, TestDomain, WritePolicy>::from(0);
+    let barrier = SpinBarrier::new(2);
+    thread::scope(|s| {
+        s.spawn(barrier.wrap(|| {
+            for i in 1..=2 {
+                group.store_all((&a, &b), (i.into(), i.into()));
+            }
+        }));
+        barrier.wait();
+        for _ in 0..2 {
+            let (a, b) = group.snapshot((&a, &b));
+            assert_eq!(**a, **b);
+        }
+    });
+}
+
+#[test]
+fn snapshot_direct_writes() {
+    domain!(TestDomain(SLOTS));
+    let a = AtomicArc::<usize, TestDomain, WritePolicy>::from(0);
+    let b = AtomicArc::<usize, TestDomain, WritePolicy>::from(0);
+    let barrier = SpinBarrier::new(2);
+    thread::scope(|s| {
+        s.spawn(barrier.wrap(|| {
+            for i in 1..=2 {
+                a.store(i.into());
+                b.store(i.into());
+            }
+        }));
+        barrier.wait();
+        for _ in 0..2 {
+            // `a` is written first, so `b` never exceeds it at a single point in time
+            let (a, b) = hazarc::snapshot((&a, &b));
+            assert!(**a == **b || **a == **b + 1);
+        }
+    });
+}
+
+#[test]
+fn stm_transfers() {
+    domain!(TestDomain(SLOTS));
+    let x = AtomicArc::<usize, TestDomain, WritePolicy>::from(2);
+    let y = AtomicArc::<usize, TestDomain, WritePolicy>::from(0);
+    let transfer = || {
+        stm::atomically(|tx| {
+            let (vx, vy) = (**tx.read(&x)?, **tx.read(&y)?);
+            tx.write(&x, (vx - 1).into());
+            tx.write(&y, (vy + 1).into());
+            Ok(())
+        });
+    };
+    let barrier = SpinBarrier::new(3);
+    thread::scope(|s| {
+        s.spawn(barrier.wrap(transfer));
+        s.spawn(barrier.wrap(transfer));
+        barrier.wait();
+        let (vx, vy) = stm::atomically(|tx| Ok((**tx.read(&x)?, **tx.read(&y)?)));
+        assert_eq!(vx + vy, 2);
+    });
+    assert_eq!((**x.load(), **y.load()), (0, 2));
+}
+
+#[test]
+fn drop_all_with_active_borrows() {
+    domain!(TestDomain(SLOTS));
+    let arc = Arc::new(0);
+    let atomic_arcs: Vec<_> = (0..4)
+        .map(|i| {
+            AtomicArc::<usize, TestDomain, WritePolicy>::new(if i < 2 {
+                arc.clone()
+            } else {
+                Arc::new(i)
+            })
+        })
+        .collect();
+    let borrows: Vec<_> = atomic_arcs.iter().map(AtomicArc::load).collect();
+    let options = vec![
+        AtomicOptionArc::<usize, TestDomain, WritePolicy>::from(arc.clone()),
+        AtomicOptionArc::none(),
+    ];
+    let option_borrow = options[0].load();
+    let arcs = AtomicArc::into_owned_all(atomic_arcs);
+    assert_eq!(
+        arcs.iter().map(|arc| **arc).collect::<Vec<_>>(),
+        [0, 0, 2, 3]
+    );
+    assert!(borrows
+        .iter()
+        .zip(&arcs)
+        .all(|(borrow, arc)| ***borrow == **arc));
+    AtomicOptionArc::drop_all(options);
+    assert_eq!(**option_borrow.unwrap(), 0);
+    drop((arcs, borrows));
+    assert_eq!(Arc::strong_count(&arc), 1);
+}
+
+#[test]
+fn vec_concurrent_push() {
+    domain!(TestDomain(SLOTS));
+    let vec = AtomicArcVec::<usize, TestDomain, WritePolicy>::from_iter([Arc::new(0)]);
+    let barrier = SpinBarrier::new(2);
+    thread::scope(|s| {
+        s.spawn(barrier.wrap(|| vec.push(1.into())));
+        barrier.wait();
+        vec.push(2.into());
+        vec.store(0, 3.into());
+    });
+    let mut values: Vec<_> = vec.load_all().iter().map(|arc| ***arc).collect();
+    values.sort_unstable();
+    assert_eq!(values, [1, 2, 3]);
+}
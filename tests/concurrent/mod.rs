@@ -32,3 +32,53 @@ fn consecutive_loads() {
         }
     });
 }
+
+#[test]
+fn concurrent_edits() {
+    domain!(TestDomain(SLOTS));
+    let barrier = SpinBarrier::new(2);
+    let atomic_arc = AtomicArc::<usize, TestDomain, WritePolicy>::from(0);
+    thread::scope(|s| {
+        s.spawn(barrier.wrap(|| atomic_arc.update_mut(|i| *i += 1)));
+        s.spawn(barrier.wrap(|| atomic_arc.update_mut(|i| *i += 1)));
+    });
+    assert_eq!(**atomic_arc.load(), 2);
+    let mut edit = atomic_arc.edit();
+    *edit += 1;
+    atomic_arc.store(0.into());
+    assert_eq!(**edit.publish().unwrap_err(), 0);
+}
+
+#[test]
+fn conflicting_edit_drop() {
+    domain!(TestDomain(SLOTS));
+    let atomic_arc = AtomicArc::<usize, TestDomain, WritePolicy>::from(0);
+    let mut edit = atomic_arc.edit();
+    *edit += 1;
+    atomic_arc.store(2.into());
+    drop(edit);
+    assert_eq!(**atomic_arc.load(), 2);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn sharded_insert_remove() {
+    use std::sync::Arc;
+
+    use hazarc::sharded::ShardedMap;
+    domain!(TestDomain(SLOTS));
+    let map = ShardedMap::<usize, usize, TestDomain, WritePolicy>::with_shard_count(1);
+    for i in 0..8 {
+        map.insert(0, Arc::new(0));
+        let barrier = SpinBarrier::new(2);
+        let value = Arc::new(i + 1);
+        let removed = thread::scope(|s| {
+            s.spawn(barrier.wrap(|| map.insert(0, value.clone())));
+            barrier.wait();
+            map.remove(&0)
+        });
+        // the inserted value is either removed or still stored
+        let stored = map.get(&0).map(|arc| **arc);
+        assert!(removed.map_or(false, |v| *v == i + 1) || stored == Some(i + 1));
+    }
+}